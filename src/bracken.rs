@@ -1,12 +1,29 @@
-use crate::kraken::Taxon;
+use crate::{
+    data::abundance::{AbundanceData, ReadCounts},
+    kraken::Taxon,
+};
 
-#[derive(Clone, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
+pub type BrackenData = AbundanceData<AbundanceValues>;
+
+#[derive(Clone, PartialEq, PartialOrd, Debug, Serialize, Deserialize, Default)]
 pub struct AbundanceValues {
     pub kraken_assigned_reads: u64,
     pub added_reads: u64,
     pub new_est_reads: u64,
     pub fraction_total_reads: f64,
 }
+
+/// Bracken estimates the reads of whole clades, both counts are the new estimated reads
+impl ReadCounts for AbundanceValues {
+    fn clade_reads(&self) -> u64 {
+        self.new_est_reads
+    }
+
+    fn taxon_reads(&self) -> u64 {
+        self.new_est_reads
+    }
+}
+
 #[derive(Clone, PartialEq, PartialOrd, Debug, Deserialize)]
 pub struct BrackenRecord {
     #[serde(flatten)]
@@ -17,26 +34,40 @@ pub struct BrackenRecord {
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
-    // use indextree::Arena;
-    // use std::collections::BTreeMap;
-
-    // #[test]
-    // fn bracken_works() {
-    //     let mut rdr = csv::ReaderBuilder::new()
-    //         .has_headers(true)
-    //         .delimiter(b'\t')
-    //         .from_path(r"C:\Users\Jean\Documents\spideog\_test_data\Sam9_species.bracken")
-    //         .unwrap();
-    //     // dbg!(rdr);
-
-    //     let mut bracken = BTreeMap::new();
-
-    //     for result in rdr.deserialize() {
-    //         let record: BrackenRecord = result.unwrap();
-    //         bracken.insert(record.organism, record.abundance_values);
-    //     }
-
-    //     println!("{:?}", bracken);
-    // }
+    use super::*;
+    use crate::taxonomy::Rank;
+
+    static BRACKEN: &str = "name\ttaxonomy_id\ttaxonomy_lvl\tkraken_assigned_reads\tadded_reads\tnew_est_reads\tfraction_total_reads
+Homo sapiens\t9606\tS\t50\t3\t53\t0.14324
+Escherichia coli\t562\tS\t30\t2\t32\t0.08649
+";
+
+    #[test]
+    fn bracken_works() {
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .delimiter(b'\t')
+            .from_reader(BRACKEN.as_bytes());
+
+        let records: Vec<BrackenRecord> = rdr.deserialize().map(Result::unwrap).collect();
+
+        pretty_assertions::assert_eq!(records.len(), 2);
+        pretty_assertions::assert_eq!(
+            records[0].taxon,
+            Taxon {
+                taxonomy_level: Rank::Species(0),
+                name: "Homo sapiens".to_string(),
+                taxonomy_id: 9606,
+            }
+        );
+        pretty_assertions::assert_eq!(
+            records[1].abundance_values,
+            AbundanceValues {
+                kraken_assigned_reads: 30,
+                added_reads: 2,
+                new_est_reads: 32,
+                fraction_total_reads: 0.08649,
+            }
+        );
+    }
 }
@@ -47,7 +47,8 @@ pub fn install_tracing() {
     use tracing_subscriber::prelude::*;
     use tracing_subscriber::{fmt, EnvFilter};
 
-    let fmt_layer = fmt::layer().with_target(false);
+    // logs go to stderr, stdout is kept for the outputs
+    let fmt_layer = fmt::layer().with_target(false).with_writer(std::io::stderr);
     let filter_layer = EnvFilter::try_from_default_env()
         .or_else(|_| EnvFilter::try_new("debug"))
         .unwrap();
@@ -35,28 +35,34 @@ use clap::{Clap, ValueHint};
 
 #[derive(Clap, Debug)]
 pub struct SingleReport {
-    /// A single Kraken report
+    /// A single Kraken report (`-` to read from the standard input)
     #[clap(name = "FILE", parse(from_os_str), value_hint = ValueHint::AnyPath, required(true), multiple(false), takes_value(true))]
     pub path: PathBuf,
-    /// Input report format
-    #[clap(long = "report-format", name = "report-format", arg_enum, case_insensitive(true), global(true), default_value("Kraken"))]
-    pub format: crate::io::InputReportFormat,
+    /// Input report format [default: detected from the content]
+    #[clap(long = "report-format", name = "report-format", arg_enum, case_insensitive(true), global(true))]
+    pub format: Option<crate::io::InputReportFormat>,
     /// Does the kraken report has headers
     #[clap(long = "has-headers", takes_value(false))]
-    pub headers: bool
+    pub headers: bool,
+    /// Taxonomy (NCBI taxdump directory, Kraken2 database directory, or ktaxonomy.tsv) to check taxids and add missing ancestors, required for Kraken per-read classifications
+    #[clap(long = "taxonomy", parse(from_os_str), value_hint = ValueHint::AnyPath, takes_value(true))]
+    pub taxonomy: Option<PathBuf>,
 }
 
 #[derive(Clap, Debug)]
 pub struct MultipleReports {
-    /// Multiple Kraken reports
+    /// Multiple Kraken reports (`-` to read one of them from the standard input)
     #[clap(name = "FILES", parse(from_os_str), value_hint = ValueHint::AnyPath, required(true), multiple(true), takes_value(true))]
     pub paths: Vec<PathBuf>,
-    /// Input reports format (all reports must have the format)
-    #[clap(long = "report-format", name = "report-format", arg_enum, case_insensitive(true), global(true), default_value("Kraken"))]
-    pub format: crate::io::InputReportFormat,
+    /// Input reports format (all reports must have the format) [default: detected for each report]
+    #[clap(long = "report-format", name = "report-format", arg_enum, case_insensitive(true), global(true))]
+    pub format: Option<crate::io::InputReportFormat>,
     /// Does the kraken reports have headers (all or none)
     #[clap(long = "have-headers", takes_value(false))]
-    pub headers: bool
+    pub headers: bool,
+    /// Taxonomy (NCBI taxdump directory, Kraken2 database directory, or ktaxonomy.tsv) to check taxids and add missing ancestors, required for Kraken per-read classifications
+    #[clap(long = "taxonomy", parse(from_os_str), value_hint = ValueHint::AnyPath, takes_value(true))]
+    pub taxonomy: Option<PathBuf>,
 }
 
 #[derive(Clap, Debug, Clone)]
@@ -80,11 +86,12 @@ pub struct OutputFile {
     pub overwrite: bool,
 }
 
+#[allow(dead_code)]
 #[derive(Clap, Debug)]
 pub struct InputReport {
-    /// Input report format
-    #[clap(long = "report-format", name = "report-format", arg_enum, case_insensitive(true), global(true), default_value("Kraken"))]
-    pub format: crate::io::InputReportFormat,
+    /// Input report format [default: detected from the content]
+    #[clap(long = "report-format", name = "report-format", arg_enum, case_insensitive(true), global(true))]
+    pub format: Option<crate::io::InputReportFormat>,
 }
 
 #[derive(Clap, Debug)]
@@ -94,6 +101,18 @@ pub struct OutputPhylo {
     /// Output tree format
     #[clap(long = "format", name = "output-format", arg_enum, case_insensitive(true), default_value("Newick"))]
     pub format: crate::io::OutputPhyloFormat,
+    /// mpa output: only write the canonical ranks (domain, kingdom, phylum, class, order, family, genus, and species)
+    #[clap(long = "canonical-ranks", takes_value(false))]
+    pub canonical_ranks: bool,
+    /// Newick and NHX outputs: branch lengths from the indents of the report, the number of ranks (canonical ranks and sub-ranks) between the taxa, all of one, or none
+    #[clap(long = "branch-length", arg_enum, case_insensitive(true), default_value("indent"))]
+    pub branch_length: crate::io::BranchLength,
+    /// Newick and NHX outputs: labels with cleaned names, quoted original names, taxids, or cleaned names and taxids (`name|taxid`)
+    #[clap(long = "label", arg_enum, case_insensitive(true), default_value("clean"))]
+    pub label: crate::io::NewickLabel,
+    /// krona-html output: local copy of Krona's `krona-2.0.js` embedded in the chart, instead of loading it from the Krona website
+    #[clap(long = "krona-js", parse(from_os_str), value_hint = ValueHint::FilePath, takes_value(true))]
+    pub krona_js: Option<PathBuf>,
 }
 
 
@@ -104,6 +123,57 @@ pub struct OutputAbundance {
     /// Output abundance format
     #[clap(long = "format", name = "output-format", arg_enum, case_insensitive(true), default_value("csv"))]
     pub format: crate::io::OutputAbundanceFormat,
+    /// mpa output: only write the canonical ranks (domain, kingdom, phylum, class, order, family, genus, and species)
+    #[clap(long = "canonical-ranks", takes_value(false))]
+    pub canonical_ranks: bool,
+    /// CSV output: add the names of the canonical ranks in the lineage of each taxon, and the lineage as a single `d__...;p__...` string
+    #[clap(long = "lineage", takes_value(false))]
+    pub lineage: bool,
 }
 
+#[derive(Clap, Debug)]
+pub struct DiversityCounts {
+    /// Rank of the taxa counted, the sub-ranks (e.g. strains) are counted with their ancestor at the rank
+    #[clap(long = "rank", arg_enum, case_insensitive(true), default_value("species"))]
+    pub rank: crate::io::CanonicalRank,
+    /// Reads counted for each taxon: the reads of its clade, or the reads assigned directly to the taxon and the taxa below it
+    #[clap(long = "reads", arg_enum, case_insensitive(true), default_value("clade-reads"))]
+    pub reads: crate::io::DiversityReads,
+}
+
+#[derive(Clap, Debug)]
+pub struct OutputDiversity {
+    #[clap(flatten)]
+    pub file: OutputFile,
+    /// Output format
+    #[clap(long = "format", name = "output-format", arg_enum, case_insensitive(true), default_value("csv"))]
+    pub format: crate::io::OutputDiversityFormat,
+}
 
+#[derive(Clap, Debug)]
+pub struct OutputDistance {
+    #[clap(flatten)]
+    pub file: OutputFile,
+    /// Output distance matrix format, the PHYLIP names are truncated to ten characters
+    #[clap(long = "format", name = "output-format", arg_enum, case_insensitive(true), default_value("tsv"))]
+    pub format: crate::io::OutputDistanceFormat,
+}
+
+#[derive(Clap, Debug)]
+pub struct OutputRarefied {
+    /// Output directory, created if missing, with one file per report named after the report
+    #[clap(long = "output-dir", parse(from_os_str), value_hint = ValueHint::DirPath, takes_value(true))]
+    pub directory: PathBuf,
+    /// force overwriting existing files in the output directory
+    #[clap(long = "overwrite", takes_value(false))]
+    pub overwrite: bool,
+    /// Output format: a Kraken report or an abundance table
+    #[clap(long = "format", name = "output-format", arg_enum, case_insensitive(true), default_value("kraken"))]
+    pub format: crate::io::OutputRarefiedFormat,
+    /// mpa output: only write the canonical ranks (domain, kingdom, phylum, class, order, family, genus, and species)
+    #[clap(long = "canonical-ranks", takes_value(false))]
+    pub canonical_ranks: bool,
+    /// CSV output: add the names of the canonical ranks in the lineage of each taxon, and the lineage as a single `d__...;p__...` string
+    #[clap(long = "lineage", takes_value(false))]
+    pub lineage: bool,
+}
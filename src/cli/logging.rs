@@ -5,6 +5,7 @@ use std::fs::OpenOptions;
 use std::path::PathBuf;
 use tracing::instrument;
 
+#[allow(dead_code)]
 #[derive(Clap, Debug)]
 pub struct Logging {
     /// Log file (stdout if not present)
@@ -15,6 +16,7 @@ pub struct Logging {
     pub verbose: bool,
 }
 
+#[allow(dead_code)]
 impl Logging {
     #[instrument]
     pub fn setup(&self) -> Result<(), Report> {
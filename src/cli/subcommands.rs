@@ -1,4 +1,9 @@
-use super::args::{MultipleReports, OutputAbundance, OutputPhylo, SingleReport};
+use super::args::{
+    DiversityCounts, MultipleReports, OutputAbundance, OutputDistance, OutputDiversity,
+    OutputPhylo, OutputRarefied, SingleReport,
+};
+use clap::ValueHint;
+use std::path::PathBuf;
 #[derive(Clap, Debug)]
 #[non_exhaustive]
 pub enum Command {
@@ -7,10 +12,14 @@ pub enum Command {
     ConvertAbundance(ConvertAbundance),
     CombineTrees(CombineTrees),
     CombineAbundances(CombineAbundances),
+    ExportPhyloseq(ExportPhyloseq),
+    Diversity(Diversity),
+    Rarefy(Rarefy),
     // Track(Track),
 }
 
 /// Extract diverse information about multiple reports
+#[allow(dead_code)]
 #[derive(Clap, Debug)]
 #[clap(after_help = super::AFTER_HELP)]
 pub struct Info {
@@ -21,6 +30,7 @@ pub struct Info {
 }
 
 /// Track one or multiple species across multiple reports
+#[allow(dead_code)]
 #[derive(Clap, Debug)]
 #[clap(after_help = super::AFTER_HELP)]
 pub struct Track {
@@ -71,6 +81,139 @@ pub struct CombineAbundances {
     /// add missing taxons for each sample
     #[clap(long = "add-missing-taxons", takes_value(false))]
     pub add_missing_taxons: bool,
+    /// CSV layout: one row per sample and taxon (long), or one row per taxon and one column per sample (wide)
+    #[clap(
+        long = "layout",
+        arg_enum,
+        case_insensitive(true),
+        default_value("long")
+    )]
+    pub layout: crate::io::AbundanceLayout,
+    /// Value in the sample columns of the wide layout [default: taxon-reads]
+    #[clap(long = "value", arg_enum, case_insensitive(true))]
+    pub value: Option<crate::io::AbundanceValue>,
+}
+
+/// Export multiple reports to the tables and tree of a phyloseq object
+#[derive(Clap, Debug)]
+#[clap(after_help = super::AFTER_HELP)]
+pub struct ExportPhyloseq {
+    #[clap(flatten)]
+    pub input: MultipleReports,
+    /// Output directory, created if missing
+    #[clap(
+        long = "output-dir",
+        parse(from_os_str),
+        value_hint = ValueHint::DirPath,
+        takes_value(true)
+    )]
+    pub directory: PathBuf,
+    /// force overwriting existing files in the output directory
+    #[clap(long = "overwrite", takes_value(false))]
+    pub overwrite: bool,
+    /// CSV table of sample metadata, with the names of the reports (without their extensions) in the first column
+    #[clap(
+        long = "sample-data",
+        parse(from_os_str),
+        value_hint = ValueHint::FilePath,
+        takes_value(true)
+    )]
+    pub sample_data: Option<PathBuf>,
+}
+
+/// Diversity of the taxa counted at a rank
+#[derive(Clap, Debug)]
+#[clap(after_help = super::AFTER_HELP)]
+pub struct Diversity {
+    #[clap(subcommand)]
+    pub command: DiversityCommand,
+}
+
+#[derive(Clap, Debug)]
+pub enum DiversityCommand {
+    Alpha(AlphaDiversity),
+    Beta(BetaDiversity),
+    Unifrac(UnifracDistance),
+}
+
+/// Alpha diversity indices of each report
+#[derive(Clap, Debug)]
+#[clap(after_help = super::AFTER_HELP)]
+pub struct AlphaDiversity {
+    #[clap(flatten)]
+    pub input: MultipleReports,
+    #[clap(flatten)]
+    pub counts: DiversityCounts,
+    #[clap(flatten)]
+    pub output: OutputDiversity,
+    /// Faith's PD and taxonomic distinctness: branch lengths from the number of ranks (canonical ranks and sub-ranks) between the taxa, the indents of the reports, or all of one
+    #[clap(
+        long = "branch-length",
+        arg_enum,
+        case_insensitive(true),
+        default_value("rank")
+    )]
+    pub branch_length: crate::io::DiversityBranchLength,
+}
+
+/// Distances between the reports
+#[derive(Clap, Debug)]
+#[clap(after_help = super::AFTER_HELP)]
+pub struct BetaDiversity {
+    #[clap(flatten)]
+    pub input: MultipleReports,
+    #[clap(flatten)]
+    pub counts: DiversityCounts,
+    #[clap(flatten)]
+    pub output: OutputDistance,
+    /// Distance between two reports
+    #[clap(
+        long = "metric",
+        arg_enum,
+        case_insensitive(true),
+        default_value("bray-curtis")
+    )]
+    pub metric: crate::io::DistanceMetric,
+    /// Aitchison distance: pseudocount added to the reads to take the logarithm of the zeros
+    #[clap(long = "pseudocount", default_value("1"))]
+    pub pseudocount: f64,
+}
+
+/// UniFrac distances between the reports, on their combined taxonomy tree
+#[derive(Clap, Debug)]
+#[clap(after_help = super::AFTER_HELP)]
+pub struct UnifracDistance {
+    #[clap(flatten)]
+    pub input: MultipleReports,
+    #[clap(flatten)]
+    pub output: OutputDistance,
+    /// Weighted (normalized) UniFrac from the proportions of the reads, instead of the unweighted UniFrac from the presence of the taxa
+    #[clap(long = "weighted", takes_value(false))]
+    pub weighted: bool,
+    /// Branch lengths from the indents of the reports, the number of ranks (canonical ranks and sub-ranks) between the taxa, or all of one
+    #[clap(
+        long = "branch-length",
+        arg_enum,
+        case_insensitive(true),
+        default_value("indent")
+    )]
+    pub branch_length: crate::io::DiversityBranchLength,
+}
+
+/// Subsample the reads of multiple reports to the same depth
+#[derive(Clap, Debug)]
+#[clap(after_help = super::AFTER_HELP)]
+pub struct Rarefy {
+    #[clap(flatten)]
+    pub input: MultipleReports,
+    #[clap(flatten)]
+    pub output: OutputRarefied,
+    /// Number of reads drawn without replacement, including the unclassified reads
+    #[clap(long = "depth", required(true))]
+    pub depth: u64,
+    /// Seed of the random number generator, the same seed gives the same subsample, each report is subsampled with this seed
+    #[clap(long = "seed", default_value("0"))]
+    pub seed: u64,
 }
 
 pub trait Runner {
@@ -1,2 +1,4 @@
 pub mod abundance;
+#[cfg(test)]
+pub(crate) mod fixtures;
 pub mod tree;
@@ -1,27 +1,158 @@
-use std::collections::HashMap;
+use serde::{Serialize, Serializer};
+use std::collections::{hash_map, HashMap};
 use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
+use tracing::warn;
 
-use crate::kraken::{Fragments, Taxon};
+use crate::{
+    errors::SpideogError,
+    kraken::{Fragments, Taxon},
+    taxonomy::index::TaxonomyIndex,
+};
 
-pub type AbundanceData = HashMap<Taxon, Fragments>;
+/// Taxa from the root to a taxon (included)
+pub type Lineage = Vec<Taxon>;
+
+/// Lineages of the taxa, when they are known
+pub type Lineages = HashMap<Taxon, Lineage>;
+
+/// Abundance values of the taxa of a report, used as a map from the taxa to their values
+#[derive(Debug, Clone, PartialEq)]
+pub struct AbundanceData<V = Fragments> {
+    values: HashMap<Taxon, V>,
+    pub lineages: Lineages,
+}
+
+impl<V> AbundanceData<V> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+            lineages: HashMap::new(),
+        }
+    }
+
+    /// Lineage of a taxon, when it is known
+    #[must_use]
+    pub fn lineage(&self, taxon: &Taxon) -> Option<&Lineage> {
+        self.lineages.get(taxon)
+    }
+}
+
+impl<V> Default for AbundanceData<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Deref for AbundanceData<V> {
+    type Target = HashMap<Taxon, V>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.values
+    }
+}
+
+impl<V> DerefMut for AbundanceData<V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.values
+    }
+}
+
+impl<V> FromIterator<(Taxon, V)> for AbundanceData<V> {
+    fn from_iter<T: IntoIterator<Item = (Taxon, V)>>(iter: T) -> Self {
+        Self {
+            values: iter.into_iter().collect(),
+            lineages: HashMap::new(),
+        }
+    }
+}
+
+impl<V> IntoIterator for AbundanceData<V> {
+    type Item = (Taxon, V);
+    type IntoIter = hash_map::IntoIter<Taxon, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter()
+    }
+}
+
+impl<'a, V> IntoIterator for &'a AbundanceData<V> {
+    type Item = (&'a Taxon, &'a V);
+    type IntoIter = hash_map::Iter<'a, Taxon, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
+
+/// Reads of the clade of a taxon, and reads assigned directly to the taxon
+pub trait ReadCounts {
+    fn clade_reads(&self) -> u64;
+    fn taxon_reads(&self) -> u64;
+}
+
+/// Check the taxa of a dataset against a taxonomy, see [`TaxonomyIndex::resolve_taxon`], and take
+/// their lineages from the taxonomy
+pub fn resolve_taxa<V>(
+    data: AbundanceData<V>,
+    taxonomy: &TaxonomyIndex,
+) -> Result<AbundanceData<V>, SpideogError> {
+    let AbundanceData {
+        values: data,
+        mut lineages,
+    } = data;
+    let mut resolved = AbundanceData::new();
+
+    for (taxon, values) in data {
+        let lineage = lineages.remove(&taxon);
+        let taxon = taxonomy.resolve_taxon(&taxon).unwrap_or_else(|| {
+            warn!(
+                "taxid `{}` ({}) is not in the taxonomy, it is kept as is",
+                taxon.taxonomy_id, taxon.name
+            );
+            taxon
+        });
+
+        let lineage = if taxonomy.contains(taxon.taxonomy_id) {
+            Some(
+                taxonomy
+                    .lineage(taxon.taxonomy_id)?
+                    .into_iter()
+                    .filter_map(|taxonomy_id| taxonomy.get(taxonomy_id))
+                    .map(Taxon::from)
+                    .collect(),
+            )
+        } else {
+            lineage
+        };
+        if let Some(lineage) = lineage {
+            resolved.lineages.insert(taxon.clone(), lineage);
+        }
+
+        resolved.insert(taxon, values);
+    }
+
+    Ok(resolved)
+}
 
 pub type SampleName = String;
 
 #[derive(Debug, Default, PartialEq)]
-pub struct SampleAbundance {
+pub struct SampleAbundance<V = Fragments> {
     pub name: SampleName,
-    pub dataset: AbundanceData,
+    pub dataset: AbundanceData<V>,
 }
 
-impl SampleAbundance {
+impl<V> SampleAbundance<V> {
     #[must_use]
     pub fn taxons(&self) -> Vec<Taxon> {
         self.dataset.keys().cloned().collect()
     }
 }
 
-impl From<(SampleName, AbundanceData)> for SampleAbundance {
-    fn from(values: (SampleName, AbundanceData)) -> Self {
+impl<V> From<(SampleName, AbundanceData<V>)> for SampleAbundance<V> {
+    fn from(values: (SampleName, AbundanceData<V>)) -> Self {
         Self {
             name: values.0,
             dataset: values.1,
@@ -31,24 +162,37 @@ impl From<(SampleName, AbundanceData)> for SampleAbundance {
 
 pub type SamplesAbundanceData = Vec<SampleAbundance>; // FIXME remove
 
-#[derive(Debug, Default, PartialEq)]
-pub struct Samples {
-    pub data: Vec<SampleAbundance>,
+#[derive(Debug, PartialEq)]
+pub struct Samples<V = Fragments> {
+    pub data: Vec<SampleAbundance<V>>,
     pub unique_taxons: Vec<Taxon>,
+    /// Lineages of the taxa, taken from the first sample where they are known
+    pub lineages: Lineages,
+}
+
+impl<V> Default for Samples<V> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl Samples {
+impl<V> Samples<V> {
     #[must_use]
     pub fn new() -> Self {
         Self {
             data: Vec::new(),
             unique_taxons: Vec::new(),
+            lineages: HashMap::new(),
         }
     }
 
-    fn add(&mut self, elem: SampleAbundance) {
+    fn add(&mut self, mut elem: SampleAbundance<V>) {
         let new_taxons = elem.taxons();
 
+        for (taxon, lineage) in elem.dataset.lineages.drain() {
+            self.lineages.entry(taxon).or_insert(lineage);
+        }
+
         for taxon in new_taxons {
             if !self.unique_taxons.contains(&taxon) {
                 self.unique_taxons.push(taxon);
@@ -57,14 +201,59 @@ impl Samples {
 
         self.data.push(elem);
     }
+}
+
+/// Row of a samples table: a taxon of a sample and its values
+#[derive(Debug, Serialize)]
+pub struct SampleRow<'a, V> {
+    pub sample: &'a str,
+    pub taxid: u64,
+    pub name: &'a str,
+    pub rank: String,
+    #[serde(flatten)]
+    pub values: &'a V,
+}
+
+impl<V> Samples<V> {
+    /// Rows sample by sample, the taxa are in the order they were first seen
+    pub fn rows(&self) -> impl Iterator<Item = SampleRow<'_, V>> {
+        self.data.iter().flat_map(move |sample| {
+            self.unique_taxons.iter().filter_map(move |taxon| {
+                sample.dataset.get(taxon).map(|values| SampleRow {
+                    sample: &sample.name,
+                    taxid: taxon.taxonomy_id,
+                    name: &taxon.name,
+                    rank: taxon.taxonomy_level.to_string(),
+                    values,
+                })
+            })
+        })
+    }
+}
+
+/// Sequence of rows, see [`Samples::rows`]
+impl<V: Serialize> Serialize for Samples<V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.rows())
+    }
+}
+
+impl<V> Samples<V> {
+    /// Lineage of a taxon, from the first sample where it is known
+    #[must_use]
+    pub fn lineage(&self, taxon: &Taxon) -> Option<&Lineage> {
+        self.lineages.get(taxon)
+    }
+}
 
+impl<V: Default> Samples<V> {
     pub fn add_missing_taxons(&mut self) -> &mut Self {
         for datum in &mut self.data {
             for taxon in &self.unique_taxons {
                 datum
                     .dataset
                     .entry(taxon.clone())
-                    .or_insert_with(Fragments::default);
+                    .or_insert_with(V::default);
             }
         }
 
@@ -72,8 +261,8 @@ impl Samples {
     }
 }
 
-impl FromIterator<(SampleName, AbundanceData)> for Samples {
-    fn from_iter<T: IntoIterator<Item = (SampleName, AbundanceData)>>(iter: T) -> Self {
+impl<V> FromIterator<(SampleName, AbundanceData<V>)> for Samples<V> {
+    fn from_iter<T: IntoIterator<Item = (SampleName, AbundanceData<V>)>>(iter: T) -> Self {
         let mut samples = Self::new();
 
         for i in iter {
@@ -0,0 +1,67 @@
+use daggy::NodeIndex;
+
+use crate::{
+    data::tree::{IndentedTaxon, Reads, Tree},
+    kraken::Taxon,
+    taxonomy::Rank,
+};
+
+pub(crate) fn node(
+    taxonomy_id: u64,
+    name: &str,
+    taxonomy_level: Rank,
+    indent: usize,
+    reads: Option<(u64, u64)>,
+) -> IndentedTaxon {
+    IndentedTaxon {
+        indent,
+        taxon: Taxon::new(taxonomy_id, name, taxonomy_level),
+        reads: reads.map(|(clade, taxon)| Reads { clade, taxon }),
+    }
+}
+
+/// Unclassified and root, then two species of Escherichia and Staphylococcus aureus three indents
+/// below the root, 100 reads in total
+pub(crate) fn tree() -> Tree {
+    let mut tree = Tree::new();
+    tree.with_origin(node(
+        0,
+        "unclassified",
+        Rank::Unclassified(0),
+        0,
+        Some((40, 40)),
+    ));
+    tree.child(
+        NodeIndex::new(0),
+        node(1, "root", Rank::Root(0), 0, Some((60, 5))),
+    );
+    tree.child(
+        NodeIndex::new(1),
+        node(561, "Escherichia", Rank::Genus(0), 1, Some((45, 15))),
+    );
+    tree.child(
+        NodeIndex::new(2),
+        node(562, "Escherichia coli", Rank::Species(0), 2, Some((30, 30))),
+    );
+    tree.child(
+        NodeIndex::new(2),
+        node(
+            208_962,
+            "Escherichia albertii",
+            Rank::Species(0),
+            2,
+            Some((0, 0)),
+        ),
+    );
+    tree.child(
+        NodeIndex::new(1),
+        node(
+            1280,
+            "Staphylococcus aureus",
+            Rank::Species(0),
+            3,
+            Some((10, 10)),
+        ),
+    );
+    tree
+}
@@ -1,20 +1,63 @@
 use color_eyre::Report;
 use core::convert::TryFrom;
-use std::fmt::Display;
+use serde::{
+    ser::{Error, SerializeMap},
+    Serialize, Serializer,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    ops::AddAssign,
+};
 
 use daggy::{Dag, NodeIndex, Walker};
-use tracing::instrument;
+use tracing::{instrument, warn};
 
 use crate::{
     errors::SpideogError,
-    kraken::{ReportRecord, Taxon},
+    kraken::{KrakenRecord, ReportRecord, Taxon},
     parser::parse_ident_organism_name,
+    taxonomy::index::{TaxonomyIndex, ROOT_TAXONOMY_ID},
 };
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+/// Reads of a node, unknown for the nodes added from a taxonomy
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default, Serialize)]
+pub struct Reads {
+    #[serde(rename = "clade_count_reads")]
+    pub clade: u64,
+    #[serde(rename = "taxon_count_reads")]
+    pub taxon: u64,
+}
+
+impl AddAssign for Reads {
+    fn add_assign(&mut self, rhs: Self) {
+        self.clade += rhs.clade;
+        self.taxon += rhs.taxon;
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct IndentedTaxon {
     pub indent: usize,
     pub taxon: Taxon,
+    pub reads: Option<Reads>,
+}
+
+/// Nodes are identified by their position and taxon, not by their reads
+impl PartialEq for IndentedTaxon {
+    fn eq(&self, other: &Self) -> bool {
+        self.indent == other.indent && self.taxon == other.taxon
+    }
+}
+
+impl Eq for IndentedTaxon {}
+
+impl Serialize for IndentedTaxon {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(5))?;
+        self.serialize_entries(&mut map)?;
+        map.end()
+    }
 }
 
 impl Display for IndentedTaxon {
@@ -28,6 +71,14 @@ impl IndentedTaxon {
     pub fn inferior_indent(&self, than: &Self) -> bool {
         self.indent < than.indent
     }
+
+    fn serialize_entries<M: SerializeMap>(&self, map: &mut M) -> Result<(), M::Error> {
+        map.serialize_entry("taxid", &self.taxon.taxonomy_id)?;
+        map.serialize_entry("name", &self.taxon.name)?;
+        map.serialize_entry("rank", &self.taxon.taxonomy_level.to_string())?;
+        map.serialize_entry("indent", &self.indent)?;
+        map.serialize_entry("reads", &self.reads)
+    }
 }
 
 impl TryFrom<ReportRecord> for IndentedTaxon {
@@ -46,23 +97,78 @@ impl TryFrom<ReportRecord> for IndentedTaxon {
         let node = Self {
             indent,
             taxon: organism_tree,
+            reads: Some(Reads {
+                clade: value.1,
+                taxon: value.2,
+            }),
         };
 
         Ok(node)
     }
 }
 
+impl TryFrom<KrakenRecord> for IndentedTaxon {
+    type Error = SpideogError;
+
+    fn try_from(value: KrakenRecord) -> Result<Self, Self::Error> {
+        Self::try_from(value.split().0)
+    }
+}
+
 pub trait TaxonomyTreeReader<T>: Sized {
     fn read(_: T) -> Result<Self, Report>;
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Tree {
     pub tree: Dag<IndentedTaxon, u32, u32>,
     pub origin: Option<NodeIndex>,
     pub last_node_added_id: Option<NodeIndex>,
 }
 
+/// Nested hierarchy from the origin, each node has a `children` list
+impl Serialize for Tree {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.origin {
+            Some(origin) => TreeNode {
+                tree: self,
+                node: origin,
+            }
+            .serialize(serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+struct TreeNode<'a> {
+    tree: &'a Tree,
+    node: NodeIndex,
+}
+
+impl Serialize for TreeNode<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let node = self
+            .tree
+            .tree
+            .node_weight(self.node)
+            .ok_or_else(|| S::Error::custom(SpideogError::NodeNotFound))?;
+        let children: Vec<TreeNode> = self
+            .tree
+            .children_of(self.node)
+            .into_iter()
+            .map(|child| TreeNode {
+                tree: self.tree,
+                node: child,
+            })
+            .collect();
+
+        let mut map = serializer.serialize_map(Some(6))?;
+        node.serialize_entries(&mut map)?;
+        map.serialize_entry("children", &children)?;
+        map.end()
+    }
+}
+
 impl Tree {
     #[must_use]
     pub fn new() -> Self {
@@ -101,18 +207,16 @@ impl Tree {
     //  find a parent with a lower indent value or default to the origin
     pub fn find_valid_parent_for(&self, taxon: &IndentedTaxon) -> Result<NodeIndex, SpideogError> {
         // default value
-        let mut parent_id = self
-            .origin
-            .ok_or_else(|| SpideogError::TreeNotInitialized)?;
+        let mut parent_id = self.origin.ok_or(SpideogError::TreeNotInitialized)?;
 
         let previously_added_node = self
             .last_node_added_id
-            .ok_or_else(|| SpideogError::TreeNotInitialized)?;
+            .ok_or(SpideogError::TreeNotInitialized)?;
 
         if self
             .tree
             .node_weight(previously_added_node)
-            .ok_or_else(|| SpideogError::NodeNotFound)?
+            .ok_or(SpideogError::NodeNotFound)?
             .inferior_indent(taxon)
         {
             // previously added node is a suitable parent for the next taxon
@@ -128,7 +232,7 @@ impl Tree {
             let node = self
                 .tree
                 .node_weight(node_id)
-                .ok_or_else(|| SpideogError::NodeNotFound)?;
+                .ok_or(SpideogError::NodeNotFound)?;
 
             if node.inferior_indent(taxon) {
                 parent_id = node_id;
@@ -139,20 +243,344 @@ impl Tree {
         Ok(parent_id)
     }
 
+    /// Children of a node, in the order they were added
+    #[must_use]
+    pub fn children_of(&self, node: NodeIndex) -> Vec<NodeIndex> {
+        let mut children: Vec<NodeIndex> = self
+            .tree
+            .children(node)
+            .iter(&self.tree)
+            .map(|(_, child)| child)
+            .collect();
+        children.sort();
+        children
+    }
+
+    /// Weight of the edge between a node and its parent, i.e. the number of combined trees sharing it
+    #[must_use]
+    pub fn parent_edge_weight(&self, node: NodeIndex) -> Option<u32> {
+        self.parent_of(node)
+            .and_then(|(edge, _)| self.tree.edge_weight(edge))
+            .copied()
+    }
+
+    /// Reads of the nodes with known reads, by taxonomy ID
+    #[must_use]
+    pub fn reads_by_taxonomy_id(&self) -> HashMap<u64, Reads> {
+        self.tree
+            .raw_nodes()
+            .iter()
+            .filter_map(|node| {
+                node.weight
+                    .reads
+                    .map(|reads| (node.weight.taxon.taxonomy_id, reads))
+            })
+            .collect()
+    }
+
+    fn add_reads(&mut self, node: NodeIndex, reads: Option<Reads>) {
+        if let (Some(node), Some(reads)) = (self.tree.node_weight_mut(node), reads) {
+            *node.reads.get_or_insert_with(Reads::default) += reads;
+        }
+    }
+
+    fn parent_of(&self, node: NodeIndex) -> Option<(daggy::EdgeIndex, NodeIndex)> {
+        self.tree.parents(node).walk_next(&self.tree)
+    }
+
+    fn taxonomy_id_of(&self, node: NodeIndex) -> Result<u64, SpideogError> {
+        self.tree
+            .node_weight(node)
+            .map(|node| node.taxon.taxonomy_id)
+            .ok_or(SpideogError::NodeNotFound)
+    }
+
+    fn indent_of(&self, node: NodeIndex) -> Result<usize, SpideogError> {
+        self.tree
+            .node_weight(node)
+            .map(|node| node.indent)
+            .ok_or(SpideogError::NodeNotFound)
+    }
+
+    /// Increase the indent of a node and all its descendants
+    fn shift_subtree(&mut self, node: NodeIndex, shift: usize) {
+        let mut stack = vec![node];
+
+        while let Some(current) = stack.pop() {
+            if let Some(weight) = self.tree.node_weight_mut(current) {
+                weight.indent += shift;
+            }
+            let mut children = self.tree.children(current);
+            while let Some((_, child)) = children.walk_next(&self.tree) {
+                stack.push(child);
+            }
+        }
+    }
+
+    fn taxonomy_node(taxonomy: &TaxonomyIndex, taxonomy_id: u64) -> Result<Taxon, SpideogError> {
+        taxonomy
+            .get(taxonomy_id)
+            .map(Taxon::from)
+            .ok_or(SpideogError::UnknownTaxonomyId(taxonomy_id))
+    }
+
+    /// Check the taxa against a taxonomy, then add the ancestors missing from the tree (e.g. in filtered reports).
+    /// The taxa unknown to the taxonomy are kept as they are.
+    pub fn with_taxonomy(mut self, taxonomy: &TaxonomyIndex) -> Result<Self, SpideogError> {
+        for node in self.tree.node_weights_mut() {
+            match taxonomy.resolve_taxon(&node.taxon) {
+                Some(taxon) => node.taxon = taxon,
+                None => warn!(
+                    "taxid `{}` ({}) is not in the taxonomy, it is kept as is",
+                    node.taxon.taxonomy_id, node.taxon.name
+                ),
+            }
+        }
+
+        self.fill_missing_ancestors(taxonomy)?;
+
+        Ok(self)
+    }
+
+    /// Insert the ancestors given by the taxonomy between each node and its closest ancestor in the tree.
+    /// Nodes attached to a wrong parent are moved under the closest ancestor of their lineage. Nodes
+    /// unknown to the taxonomy are left untouched.
+    pub fn fill_missing_ancestors(&mut self, taxonomy: &TaxonomyIndex) -> Result<(), SpideogError> {
+        let origin = self.origin.ok_or(SpideogError::TreeNotInitialized)?;
+        let original_node_count = self.tree.node_count();
+
+        let mut nodes_by_id: HashMap<u64, NodeIndex> = self
+            .tree
+            .raw_nodes()
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.weight.taxon.taxonomy_id, NodeIndex::new(index)))
+            .collect();
+
+        // ancestors above the origin, the root becomes the new origin
+        let origin_id = self.taxonomy_id_of(origin)?;
+        if taxonomy.contains(origin_id) {
+            let ancestors = taxonomy.lineage(origin_id)?;
+            let missing = ancestors.len() - 1;
+            let origin_indent = self.indent_of(origin)?;
+            if origin_indent < missing {
+                self.shift_subtree(origin, missing - origin_indent);
+            }
+
+            let mut child = origin;
+            for taxonomy_id in ancestors.into_iter().rev().skip(1) {
+                let indent = self.indent_of(child)? - 1;
+                let taxon = Self::taxonomy_node(taxonomy, taxonomy_id)?;
+                let ancestor = self.tree.add_node(IndentedTaxon {
+                    indent,
+                    taxon,
+                    reads: None,
+                });
+                self.tree
+                    .add_edge(ancestor, child, 1)
+                    .map_err(|_| SpideogError::NodeNotFound)?;
+                nodes_by_id.insert(taxonomy_id, ancestor);
+                child = ancestor;
+            }
+
+            self.origin = Some(child);
+        }
+
+        for index in 0..original_node_count {
+            let node = NodeIndex::new(index);
+            let (edge, parent) = match self.parent_of(node) {
+                Some(parent) => parent,
+                None => continue,
+            };
+
+            let taxonomy_id = self.taxonomy_id_of(node)?;
+            if !taxonomy.contains(taxonomy_id) {
+                continue;
+            }
+            let lineage = taxonomy.lineage(taxonomy_id)?;
+
+            // closest ancestor in the tree that is part of the lineage
+            let mut anchor = Some(parent);
+            let mut position = None;
+            while let Some(candidate) = anchor {
+                let candidate_id = self.taxonomy_id_of(candidate)?;
+                position = lineage.iter().position(|id| *id == candidate_id);
+                if position.is_some() {
+                    break;
+                }
+                anchor = self.parent_of(candidate).map(|(_, node)| node);
+            }
+
+            let (anchor, position) = match (anchor, position) {
+                (Some(anchor), Some(position)) => (anchor, position),
+                _ => continue,
+            };
+
+            let mut current = anchor;
+            for ancestor_id in &lineage[position + 1..lineage.len() - 1] {
+                current = match nodes_by_id.get(ancestor_id) {
+                    Some(existing) => *existing,
+                    None => {
+                        let indent = self.indent_of(current)? + 1;
+                        let taxon = Self::taxonomy_node(taxonomy, *ancestor_id)?;
+                        let (_, ancestor) = self.tree.add_child(
+                            current,
+                            1,
+                            IndentedTaxon {
+                                indent,
+                                taxon,
+                                reads: None,
+                            },
+                        );
+                        nodes_by_id.insert(*ancestor_id, ancestor);
+                        ancestor
+                    }
+                };
+            }
+
+            if current != parent {
+                let weight = *self.tree.edge_weight(edge).unwrap_or(&1);
+                // a cycle means an inconsistent tree, the node is left where it is
+                if self.tree.add_edge(current, node, weight).is_err() {
+                    continue;
+                }
+                self.tree.remove_edge(edge);
+            }
+
+            // make room below the inserted ancestors
+            let required_indent = self.indent_of(current)? + 1;
+            let indent = self.indent_of(node)?;
+            if indent < required_indent {
+                self.shift_subtree(node, required_indent - indent);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Combine trees on the backbone of a taxonomy: the tree holds the lineages of all the taxa of
+    /// all the trees, the weight of an edge is the number of trees with the child taxon or one of its
+    /// descendants. Taxa unknown to the taxonomy are placed under the root.
+    pub fn backbone(trees: &[Self], taxonomy: &TaxonomyIndex) -> Result<Self, SpideogError> {
+        let mut counts: HashMap<u64, u32> = HashMap::new();
+        let mut unknown: HashMap<u64, (Taxon, u32)> = HashMap::new();
+        let mut reads: HashMap<u64, Reads> = HashMap::new();
+
+        for tree in trees {
+            let mut known: HashSet<u64> = HashSet::new();
+            let mut not_found: HashMap<u64, &Taxon> = HashMap::new();
+
+            for node in tree.tree.raw_nodes() {
+                let taxon = &node.weight.taxon;
+                let taxonomy_id = match taxonomy.resolve_id(taxon.taxonomy_id) {
+                    Some(taxonomy_id) => {
+                        known.extend(taxonomy.lineage(taxonomy_id)?);
+                        taxonomy_id
+                    }
+                    None => {
+                        not_found.insert(taxon.taxonomy_id, taxon);
+                        taxon.taxonomy_id
+                    }
+                };
+                if let Some(node_reads) = node.weight.reads {
+                    *reads.entry(taxonomy_id).or_default() += node_reads;
+                }
+            }
+
+            for taxonomy_id in known {
+                *counts.entry(taxonomy_id).or_default() += 1;
+            }
+            for (taxonomy_id, taxon) in not_found {
+                unknown
+                    .entry(taxonomy_id)
+                    .or_insert_with(|| (taxon.clone(), 0))
+                    .1 += 1;
+            }
+        }
+
+        let mut children: HashMap<u64, Vec<Taxon>> = HashMap::new();
+        for taxonomy_id in counts.keys() {
+            if let Some(parent_id) = taxonomy.parent(*taxonomy_id) {
+                children
+                    .entry(parent_id)
+                    .or_default()
+                    .push(Self::taxonomy_node(taxonomy, *taxonomy_id)?);
+            }
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+
+        let mut backbone = Self::new();
+        backbone.with_origin(IndentedTaxon {
+            indent: 0,
+            taxon: Self::taxonomy_node(taxonomy, ROOT_TAXONOMY_ID)?,
+            reads: reads.get(&ROOT_TAXONOMY_ID).copied(),
+        });
+        let origin = backbone.origin.ok_or(SpideogError::TreeNotInitialized)?;
+
+        let mut stack = vec![(origin, ROOT_TAXONOMY_ID, 0)];
+        while let Some((node, taxonomy_id, indent)) = stack.pop() {
+            for taxon in children
+                .remove(&taxonomy_id)
+                .unwrap_or_default()
+                .into_iter()
+                .rev()
+            {
+                let child_id = taxon.taxonomy_id;
+                let weight = counts.get(&child_id).copied().unwrap_or(1);
+                let (_, child) = backbone.tree.add_child(
+                    node,
+                    weight,
+                    IndentedTaxon {
+                        indent: indent + 1,
+                        taxon,
+                        reads: reads.get(&child_id).copied(),
+                    },
+                );
+                stack.push((child, child_id, indent + 1));
+            }
+        }
+
+        let mut unknown: Vec<(Taxon, u32)> = unknown.into_values().collect();
+        unknown.sort_by(|a, b| a.0.cmp(&b.0));
+        for (taxon, weight) in unknown {
+            let taxon_reads = reads.get(&taxon.taxonomy_id).copied();
+            backbone.child_with_weight(
+                origin,
+                IndentedTaxon {
+                    indent: 1,
+                    taxon,
+                    reads: taxon_reads,
+                },
+                weight,
+            );
+        }
+
+        Ok(backbone)
+    }
+
     pub fn try_combine_with(mut self, rhs: Self) -> Result<Self, SpideogError> {
-        if self.origin.is_none() {
-            return Ok(rhs);
+        let origin = match self.origin {
+            Some(origin) => origin,
+            None => return Ok(rhs),
+        };
+
+        if let Some(rhs_origin) = rhs.origin.and_then(|origin| rhs.tree.node_weight(origin)) {
+            if self.tree.node_weight(origin) == Some(rhs_origin) {
+                self.add_reads(origin, rhs_origin.reads);
+            }
         }
 
         for rhs_edge in rhs.tree.raw_edges().iter() {
             let rhs_edge_source = rhs
                 .tree
                 .node_weight(rhs_edge.source())
-                .ok_or_else(|| SpideogError::NodeNotFound)?;
+                .ok_or(SpideogError::NodeNotFound)?;
             let rhs_edge_target = rhs
                 .tree
                 .node_weight(rhs_edge.target())
-                .ok_or_else(|| SpideogError::NodeNotFound)?;
+                .ok_or(SpideogError::NodeNotFound)?;
 
             let original_nodes = self.tree.raw_nodes();
 
@@ -196,12 +624,14 @@ impl Tree {
                     self.child(parent, rhs_edge_target.clone());
                 }
                 (Some(s), Some(t)) => {
+                    self.add_reads(t, rhs_edge_target.reads);
+
                     // increment weight of edge
                     // FIXME: some issues with different trees, can't found node that exist
                     let original_edge = self.tree.find_edge(s, t).ok_or_else(|| {
                         SpideogError::EdgeNotFound(
-                            self.tree.node_weight(s).unwrap().clone(),
-                            self.tree.node_weight(t).unwrap().clone(),
+                            self.tree.node_weight(s).unwrap().taxon.clone(),
+                            self.tree.node_weight(t).unwrap().taxon.clone(),
                         )
                     })?;
 
@@ -227,6 +657,7 @@ impl Tree {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{data::fixtures::node, taxonomy::Rank};
     // use test_case::test_case;
 
     #[test]
@@ -238,6 +669,7 @@ mod tests {
                 name: "root".to_string(),
                 taxonomy_id: 0,
             },
+            reads: None,
         };
 
         let mut tree = Tree::new();
@@ -259,6 +691,7 @@ mod tests {
                 name: "root".to_string(),
                 taxonomy_id: 0,
             },
+            reads: None,
         };
 
         let child = IndentedTaxon {
@@ -268,6 +701,7 @@ mod tests {
                 name: "child".to_string(),
                 taxonomy_id: 1,
             },
+            reads: None,
         };
 
         let grand_child = IndentedTaxon {
@@ -277,6 +711,7 @@ mod tests {
                 name: "grand child".to_string(),
                 taxonomy_id: 2,
             },
+            reads: None,
         };
 
         let mut tree = Tree::new();
@@ -318,6 +753,7 @@ mod tests {
                 name: "root".to_string(),
                 taxonomy_id: 0,
             },
+            reads: None,
         };
 
         let child = IndentedTaxon {
@@ -327,6 +763,7 @@ mod tests {
                 name: "child".to_string(),
                 taxonomy_id: 1,
             },
+            reads: None,
         };
 
         let grand_child = IndentedTaxon {
@@ -336,6 +773,7 @@ mod tests {
                 name: "grand child".to_string(),
                 taxonomy_id: 2,
             },
+            reads: None,
         };
 
         let new_child = IndentedTaxon {
@@ -345,6 +783,7 @@ mod tests {
                 name: "new_child".to_string(),
                 taxonomy_id: 3,
             },
+            reads: None,
         };
 
         let new_child_child = IndentedTaxon {
@@ -354,6 +793,7 @@ mod tests {
                 name: "new_child_child".to_string(),
                 taxonomy_id: 4,
             },
+            reads: None,
         };
 
         let mut tree = Tree::new();
@@ -372,6 +812,223 @@ mod tests {
         pretty_assertions::assert_eq!(parent, NodeIndex::new(3));
     }
 
+    #[test]
+    fn test_with_taxonomy() {
+        let taxonomy = crate::taxonomy::index::tests::small_taxonomy();
+
+        // filtered report: only Bacteria and E. coli were kept
+        let mut tree = Tree::new();
+        tree.with_origin(node(2, "Bacteria", Rank::Domain(0), 1, None));
+        tree.child(
+            NodeIndex::new(0),
+            node(562, "Escherichia coli", Rank::Species(0), 3, None),
+        );
+        tree.child(
+            NodeIndex::new(0),
+            node(1239, "Firmicutes", Rank::Phylum(0), 2, None),
+        );
+
+        let tree = tree.with_taxonomy(&taxonomy).unwrap();
+
+        let origin = tree.tree.node_weight(tree.origin.unwrap()).unwrap();
+        pretty_assertions::assert_eq!(origin.taxon.taxonomy_id, 1);
+        pretty_assertions::assert_eq!(origin.indent, 0);
+        pretty_assertions::assert_eq!(tree.tree.node_count(), 6);
+        pretty_assertions::assert_eq!(tree.tree.edge_count(), 5);
+
+        let ecoli_parent = tree.parent_of(NodeIndex::new(1)).unwrap().1;
+        let proteobacteria = tree.tree.node_weight(ecoli_parent).unwrap();
+        pretty_assertions::assert_eq!(proteobacteria.taxon.name, "Proteobacteria");
+        // the lineage of the origin is longer than its indent, the whole tree is shifted
+        pretty_assertions::assert_eq!(proteobacteria.indent, 3);
+        pretty_assertions::assert_eq!(tree.tree.node_weight(NodeIndex::new(1)).unwrap().indent, 4);
+        pretty_assertions::assert_eq!(
+            tree.tree
+                .node_weight(NodeIndex::new(1))
+                .unwrap()
+                .taxon
+                .taxonomy_level,
+            crate::taxonomy::Rank::Species(0)
+        );
+    }
+
+    #[test]
+    fn test_with_taxonomy_unknown_taxid() {
+        let taxonomy = crate::taxonomy::index::tests::small_taxonomy();
+
+        let mut tree = Tree::new();
+        tree.with_origin(node(2, "Bacteria", Rank::Domain(0), 1, None));
+        tree.child(
+            NodeIndex::new(0),
+            node(562, "Escherichia coli", Rank::Species(0), 3, None),
+        );
+        tree.child(
+            NodeIndex::new(0),
+            node(9606, "Homo sapiens", Rank::Species(0), 2, None),
+        );
+
+        let tree = tree.with_taxonomy(&taxonomy).unwrap();
+
+        let human = tree.tree.node_weight(NodeIndex::new(2)).unwrap();
+        pretty_assertions::assert_eq!(
+            human,
+            &node(9606, "Homo sapiens", Rank::Species(0), 3, None)
+        );
+        pretty_assertions::assert_eq!(
+            tree.parent_of(NodeIndex::new(2)).unwrap().1,
+            NodeIndex::new(0)
+        );
+        pretty_assertions::assert_eq!(
+            tree.tree
+                .node_weight(NodeIndex::new(1))
+                .unwrap()
+                .taxon
+                .taxonomy_level,
+            crate::taxonomy::Rank::Species(0)
+        );
+    }
+
+    #[test]
+    fn test_backbone() {
+        let taxonomy = crate::taxonomy::index::tests::small_taxonomy();
+
+        let mut tree_1 = Tree::new();
+        tree_1.with_origin(node(0, "unclassified", Rank::Unclassified(0), 0, None));
+        tree_1.child(NodeIndex::new(0), node(1, "root", Rank::Root(0), 0, None));
+        tree_1.child(
+            NodeIndex::new(1),
+            node(562, "Escherichia coli", Rank::Species(0), 4, None),
+        );
+
+        let mut tree_2 = Tree::new();
+        tree_2.with_origin(node(1, "root", Rank::Root(0), 0, None));
+        tree_2.child(
+            NodeIndex::new(0),
+            node(1239, "Firmicutes", Rank::Phylum(0), 3, None),
+        );
+        tree_2.child(
+            NodeIndex::new(0),
+            node(562, "Escherichia coli", Rank::Species(0), 4, None),
+        );
+
+        let backbone = Tree::backbone(&[tree_1, tree_2], &taxonomy).unwrap();
+
+        // root, cellular organisms, Bacteria, Proteobacteria, E. coli, Firmicutes, and unclassified
+        pretty_assertions::assert_eq!(backbone.tree.node_count(), 7);
+        pretty_assertions::assert_eq!(backbone.tree.edge_count(), 6);
+
+        let weights: HashMap<u64, u32> = backbone
+            .tree
+            .raw_edges()
+            .iter()
+            .map(|edge| {
+                (
+                    backbone
+                        .tree
+                        .node_weight(edge.target())
+                        .unwrap()
+                        .taxon
+                        .taxonomy_id,
+                    edge.weight,
+                )
+            })
+            .collect();
+        pretty_assertions::assert_eq!(weights[&562], 2);
+        pretty_assertions::assert_eq!(weights[&1239], 1);
+        pretty_assertions::assert_eq!(weights[&0], 1);
+
+        let ecoli = backbone
+            .tree
+            .raw_nodes()
+            .iter()
+            .find(|node| node.weight.taxon.taxonomy_id == 562)
+            .unwrap();
+        pretty_assertions::assert_eq!(ecoli.weight.indent, 4);
+    }
+
+    #[test]
+    fn test_backbone_unknown_taxid() {
+        let taxonomy = crate::taxonomy::index::tests::small_taxonomy();
+
+        let trees: Vec<Tree> = (0..2)
+            .map(|_| {
+                let mut tree = Tree::new();
+                tree.with_origin(node(1, "root", Rank::Root(0), 0, None));
+                tree.child(
+                    NodeIndex::new(0),
+                    node(562, "Escherichia coli", Rank::Species(0), 4, None),
+                );
+                tree.child(
+                    NodeIndex::new(0),
+                    node(9606, "Homo sapiens", Rank::Species(0), 4, None),
+                );
+                tree.with_taxonomy(&taxonomy).unwrap()
+            })
+            .collect();
+
+        let backbone = Tree::backbone(&trees, &taxonomy).unwrap();
+
+        let human = backbone
+            .tree
+            .raw_nodes()
+            .iter()
+            .position(|node| node.weight.taxon.taxonomy_id == 9606)
+            .map(NodeIndex::new)
+            .unwrap();
+        let (edge, parent) = backbone.parent_of(human).unwrap();
+
+        // placed under the root, with the number of trees it was found in
+        pretty_assertions::assert_eq!(Some(parent), backbone.origin);
+        pretty_assertions::assert_eq!(backbone.tree.edge_weight(edge), Some(&2));
+        pretty_assertions::assert_eq!(
+            backbone.tree.node_weight(human),
+            Some(&node(9606, "Homo sapiens", Rank::Species(0), 1, None))
+        );
+    }
+
+    #[test]
+    fn test_try_combine_with_reads() {
+        let mut tree_1 = Tree::new();
+        tree_1.with_origin(node(1, "root", Rank::Root(0), 0, Some((10, 0))));
+        tree_1.child(
+            NodeIndex::new(0),
+            node(2, "Bacteria", Rank::Domain(0), 1, Some((10, 10))),
+        );
+
+        let mut tree_2 = Tree::new();
+        tree_2.with_origin(node(1, "root", Rank::Root(0), 0, Some((5, 1))));
+        tree_2.child(
+            NodeIndex::new(0),
+            node(2, "Bacteria", Rank::Domain(0), 1, Some((4, 4))),
+        );
+
+        let combined_tree = tree_1.try_combine_with(tree_2).unwrap();
+
+        pretty_assertions::assert_eq!(combined_tree.tree.node_count(), 2);
+        pretty_assertions::assert_eq!(
+            combined_tree
+                .tree
+                .node_weight(NodeIndex::new(0))
+                .unwrap()
+                .reads,
+            Some(Reads {
+                clade: 15,
+                taxon: 1
+            })
+        );
+        pretty_assertions::assert_eq!(
+            combined_tree
+                .tree
+                .node_weight(NodeIndex::new(1))
+                .unwrap()
+                .reads,
+            Some(Reads {
+                clade: 14,
+                taxon: 14
+            })
+        );
+    }
+
     #[test]
     fn test_try_combine_with() {
         let origin = IndentedTaxon {
@@ -381,6 +1038,7 @@ mod tests {
                 name: "root".to_string(),
                 taxonomy_id: 0,
             },
+            reads: None,
         };
 
         let child = IndentedTaxon {
@@ -390,6 +1048,7 @@ mod tests {
                 name: "child".to_string(),
                 taxonomy_id: 1,
             },
+            reads: None,
         };
 
         let second_child = IndentedTaxon {
@@ -399,6 +1058,7 @@ mod tests {
                 name: "second child".to_string(),
                 taxonomy_id: 2,
             },
+            reads: None,
         };
 
         let grand_child = IndentedTaxon {
@@ -408,6 +1068,7 @@ mod tests {
                 name: "grand child".to_string(),
                 taxonomy_id: 3,
             },
+            reads: None,
         };
 
         let mut tree_1 = Tree::new();
@@ -0,0 +1,236 @@
+pub mod alpha;
+pub mod beta;
+pub mod phylogenetic;
+pub mod rarefaction;
+pub mod unifrac;
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    data::{
+        abundance::{AbundanceData, ReadCounts},
+        tree::{IndentedTaxon, Tree},
+    },
+    errors::SpideogError,
+    kraken::Taxon,
+    taxonomy::Rank,
+};
+
+/// Reads counted for each taxon at a rank
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountedReads {
+    /// Reads of the clade of the taxa at the rank, the taxa below the rank are part of these clades
+    Clade,
+    /// Reads assigned directly to the taxa at the rank or below the rank, summed for each taxon at the rank
+    Taxon,
+}
+
+/// Read counts of the taxa at a rank of a sample
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RankCounts {
+    pub counts: HashMap<Taxon, u64>,
+    /// Reads of the unclassified node
+    pub unclassified: u64,
+    /// Reads assigned directly to the classified taxa above the rank, or to taxa with no ancestor at the rank
+    pub unassigned: u64,
+}
+
+impl RankCounts {
+    /// Count the reads of the taxa at a (canonical) rank, the sub-ranks such as strains (`S1`) belong to
+    /// their ancestor at the rank. The lineages of the taxa are used when they are known.
+    pub fn of<V: ReadCounts>(data: &AbundanceData<V>, rank: Rank, reads: CountedReads) -> Self {
+        let mut rank_counts = Self::default();
+
+        for (taxon, values) in data {
+            if let Rank::Unclassified(_) = taxon.taxonomy_level {
+                rank_counts.unclassified += values.taxon_reads();
+                continue;
+            }
+
+            let own = [taxon.clone()];
+            let lineage = data.lineage(taxon).map_or(&own[..], |lineage| &lineage[..]);
+            let ancestor = lineage
+                .iter()
+                .find(|ancestor| ancestor.taxonomy_level == rank);
+
+            match (ancestor, reads) {
+                (None, _) => rank_counts.unassigned += values.taxon_reads(),
+                (Some(ancestor), CountedReads::Clade) => {
+                    if ancestor.taxonomy_id == taxon.taxonomy_id {
+                        *rank_counts.counts.entry(taxon.clone()).or_insert(0) +=
+                            values.clade_reads();
+                    }
+                }
+                (Some(ancestor), CountedReads::Taxon) => {
+                    *rank_counts.counts.entry(ancestor.clone()).or_insert(0) +=
+                        values.taxon_reads();
+                }
+            }
+        }
+
+        rank_counts
+    }
+
+    /// Total of the reads counted at the rank
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+}
+
+/// Read counts of the taxa (columns) in each sample (rows), the taxa are sorted by taxid and only the
+/// taxa with reads in at least one sample are kept
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CountMatrix {
+    pub taxa: Vec<Taxon>,
+    pub counts: Vec<Vec<u64>>,
+}
+
+impl CountMatrix {
+    pub fn from_rank_counts<'a, I: IntoIterator<Item = &'a RankCounts>>(samples: I) -> Self {
+        let samples: Vec<&RankCounts> = samples.into_iter().collect();
+
+        let mut taxa: Vec<Taxon> = samples
+            .iter()
+            .flat_map(|sample| {
+                sample
+                    .counts
+                    .iter()
+                    .filter(|(_, count)| **count > 0)
+                    .map(|(taxon, _)| taxon)
+            })
+            .cloned()
+            .collect::<HashSet<Taxon>>()
+            .into_iter()
+            .collect();
+        taxa.sort_by_key(|taxon| taxon.taxonomy_id);
+
+        let counts = samples
+            .iter()
+            .map(|sample| {
+                taxa.iter()
+                    .map(|taxon| sample.counts.get(taxon).copied().unwrap_or(0))
+                    .collect()
+            })
+            .collect();
+
+        Self { taxa, counts }
+    }
+}
+
+/// Classified nodes of a tree in depth-first order (the parents come before their children), with
+/// the position of their parent. The unclassified node is left out, the root has no parent.
+fn classified_nodes(tree: &Tree) -> Result<Vec<(&IndentedTaxon, Option<usize>)>, SpideogError> {
+    let origin = tree.origin.ok_or(SpideogError::TreeNotInitialized)?;
+
+    let mut nodes: Vec<(&IndentedTaxon, Option<usize>)> = Vec::new();
+    let mut stack = vec![(origin, None)];
+    while let Some((index, parent)) = stack.pop() {
+        let node = tree
+            .tree
+            .node_weight(index)
+            .ok_or(SpideogError::NodeNotFound)?;
+
+        let position = if let Rank::Unclassified(_) = node.taxon.taxonomy_level {
+            None
+        } else {
+            nodes.push((node, parent));
+            Some(nodes.len() - 1)
+        };
+
+        for child in tree.children_of(index).into_iter().rev() {
+            stack.push((child, position));
+        }
+    }
+
+    Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{data::abundance::Lineage, kraken::Fragments};
+    use test_case::test_case;
+
+    fn data() -> AbundanceData {
+        let root = Taxon::new(1, "root", Rank::Root(0));
+        let genus = Taxon::new(561, "Escherichia", Rank::Genus(0));
+        let species = Taxon::new(562, "Escherichia coli", Rank::Species(0));
+        let strain = Taxon::new(83333, "Escherichia coli K-12", Rank::Species(1));
+        let other = Taxon::new(208_962, "Escherichia albertii", Rank::Species(0));
+
+        let fragments = |clade_count_reads, taxon_count_reads, lineage: &[&Taxon]| {
+            let fragments = Fragments {
+                clade_count_reads,
+                taxon_count_reads,
+                ..Fragments::default()
+            };
+            let lineage: Lineage = lineage.iter().map(|taxon| (*taxon).clone()).collect();
+            (fragments, lineage)
+        };
+
+        let mut data = AbundanceData::new();
+        for (taxon, (fragments, lineage)) in [
+            (
+                Taxon::new(0, "unclassified", Rank::Unclassified(0)),
+                fragments(5, 5, &[]),
+            ),
+            (root.clone(), fragments(20, 1, &[&root])),
+            (genus.clone(), fragments(19, 2, &[&root, &genus])),
+            (
+                species.clone(),
+                fragments(14, 10, &[&root, &genus, &species]),
+            ),
+            (
+                strain.clone(),
+                fragments(4, 4, &[&root, &genus, &species, &strain]),
+            ),
+            (other.clone(), fragments(3, 3, &[&root, &genus, &other])),
+        ] {
+            data.lineages.insert(taxon.clone(), lineage);
+            data.insert(taxon, fragments);
+        }
+
+        data
+    }
+
+    #[test_case(CountedReads::Clade => (vec![(562, 14), (208_962, 3)], 5, 3))]
+    #[test_case(CountedReads::Taxon => (vec![(562, 14), (208_962, 3)], 5, 3))]
+    fn test_rank_counts(reads: CountedReads) -> (Vec<(u64, u64)>, u64, u64) {
+        let rank_counts = RankCounts::of(&data(), Rank::Species(0), reads);
+        let mut counts: Vec<(u64, u64)> = rank_counts
+            .counts
+            .iter()
+            .map(|(taxon, count)| (taxon.taxonomy_id, *count))
+            .collect();
+        counts.sort_unstable();
+
+        (counts, rank_counts.unclassified, rank_counts.unassigned)
+    }
+
+    #[test]
+    fn test_count_matrix() {
+        let first = RankCounts::of(&data(), Rank::Species(0), CountedReads::Clade);
+        let mut second = RankCounts::default();
+        second.counts.insert(
+            Taxon::new(1280, "Staphylococcus aureus", Rank::Species(0)),
+            2,
+        );
+        second
+            .counts
+            .insert(Taxon::new(562, "Escherichia coli", Rank::Species(0)), 0);
+
+        let matrix = CountMatrix::from_rank_counts(vec![&first, &second]);
+        let taxa: Vec<u64> = matrix.taxa.iter().map(|taxon| taxon.taxonomy_id).collect();
+
+        pretty_assertions::assert_eq!(taxa, vec![562, 1280, 208_962]);
+        pretty_assertions::assert_eq!(matrix.counts, vec![vec![14, 0, 3], vec![0, 2, 0]]);
+    }
+
+    #[test]
+    fn test_rank_counts_genus() {
+        let rank_counts = RankCounts::of(&data(), Rank::Genus(0), CountedReads::Clade);
+        pretty_assertions::assert_eq!(rank_counts.total(), 19);
+        pretty_assertions::assert_eq!(rank_counts.unassigned, 1);
+    }
+}
@@ -0,0 +1,150 @@
+/// Alpha diversity indices of the read counts of the taxa of a sample, the indices are not defined
+/// (`None`) for a sample without reads
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlphaDiversity {
+    /// Number of taxa with reads
+    pub richness: usize,
+    /// Shannon entropy, natural logarithm
+    pub shannon: Option<f64>,
+    /// Gini-Simpson index, `1 - sum(p^2)`
+    pub simpson: Option<f64>,
+    /// Inverse Simpson index, `1 / sum(p^2)`
+    pub inverse_simpson: Option<f64>,
+    /// Proportion of the most abundant taxon
+    pub berger_parker: Option<f64>,
+    /// Bias-corrected Chao1 richness estimator
+    pub chao1: Option<f64>,
+    /// Fisher's alpha, not defined when all the taxa are singletons
+    pub fisher: Option<f64>,
+}
+
+impl AlphaDiversity {
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn from_counts(counts: &[u64]) -> Self {
+        // sorted so that the sums do not depend on the order of the taxa
+        let mut counts: Vec<u64> = counts.iter().copied().filter(|count| *count > 0).collect();
+        counts.sort_unstable();
+        let richness = counts.len();
+        let total: u64 = counts.iter().sum();
+
+        if total == 0 {
+            return Self {
+                richness,
+                shannon: None,
+                simpson: None,
+                inverse_simpson: None,
+                berger_parker: None,
+                chao1: None,
+                fisher: None,
+            };
+        }
+
+        let proportions: Vec<f64> = counts
+            .iter()
+            .map(|count| *count as f64 / total as f64)
+            .collect();
+        let dominance: f64 = proportions.iter().map(|p| p * p).sum();
+        let shannon = -proportions.iter().map(|p| p * p.ln()).sum::<f64>();
+        let max = counts.iter().max().copied().unwrap_or(0);
+
+        Self {
+            richness,
+            shannon: Some(shannon),
+            simpson: Some(1.0 - dominance),
+            inverse_simpson: Some(1.0 / dominance),
+            berger_parker: Some(max as f64 / total as f64),
+            chao1: Some(chao1(&counts)),
+            fisher: fisher_alpha(richness, total),
+        }
+    }
+}
+
+/// `S + F1 (F1 - 1) / (2 (F2 + 1))` with `F1` the singletons and `F2` the doubletons
+#[allow(clippy::cast_precision_loss)]
+fn chao1(counts: &[u64]) -> f64 {
+    let singletons = counts.iter().filter(|count| **count == 1).count() as f64;
+    let doubletons = counts.iter().filter(|count| **count == 2).count() as f64;
+
+    counts.len() as f64 + singletons * (singletons - 1.0) / (2.0 * (doubletons + 1.0))
+}
+
+/// Solve `S = alpha ln(1 + N / alpha)` by bisection, the right side increases with alpha towards `N`
+#[allow(clippy::cast_precision_loss)]
+fn fisher_alpha(richness: usize, total: u64) -> Option<f64> {
+    let richness = richness as f64;
+    let total = total as f64;
+
+    if richness < 1.0 || richness >= total {
+        return None;
+    }
+
+    let species = |alpha: f64| alpha * (total / alpha).ln_1p();
+
+    let mut low = f64::EPSILON;
+    let mut high = 1.0;
+    while species(high) < richness {
+        high *= 2.0;
+    }
+
+    for _ in 0..200 {
+        let middle = (low + high) / 2.0;
+        if species(middle) < richness {
+            low = middle;
+        } else {
+            high = middle;
+        }
+
+        if high - low <= 1e-12 * high {
+            break;
+        }
+    }
+
+    Some((low + high) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn assert_close(value: Option<f64>, expected: f64) {
+        let value = value.unwrap();
+        assert!(
+            (value - expected).abs() < 1e-6,
+            "{} is not {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_alpha_diversity() {
+        let alpha = AlphaDiversity::from_counts(&[10, 5, 1, 1, 2, 0]);
+
+        pretty_assertions::assert_eq!(alpha.richness, 5);
+        assert_close(alpha.shannon, 1.236_052_9);
+        assert_close(alpha.simpson, 0.637_119_1);
+        assert_close(alpha.inverse_simpson, 2.755_725_2);
+        assert_close(alpha.berger_parker, 10.0 / 19.0);
+        assert_close(alpha.chao1, 5.5);
+        assert_close(alpha.fisher, 2.211_559_0);
+    }
+
+    #[test_case(&[] ; "empty")]
+    #[test_case(&[0, 0] ; "no reads")]
+    fn test_alpha_diversity_undefined(counts: &[u64]) {
+        let alpha = AlphaDiversity::from_counts(counts);
+
+        pretty_assertions::assert_eq!(alpha.richness, 0);
+        assert!(alpha.shannon.is_none());
+        assert!(alpha.fisher.is_none());
+    }
+
+    #[test_case(5, 19 => Some(2.211_559))]
+    #[test_case(2, 2 => None ; "only singletons")]
+    #[test_case(0, 0 => None)]
+    fn test_fisher_alpha(richness: usize, total: u64) -> Option<f64> {
+        fisher_alpha(richness, total).map(|alpha| (alpha * 1e6).round() / 1e6)
+    }
+}
@@ -0,0 +1,147 @@
+/// Distance between the read counts of two samples, over the same taxa
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distance {
+    /// `sum(|a - b|) / sum(a + b)`
+    BrayCurtis,
+    /// Jaccard distance of the taxa with reads (presence/absence)
+    Jaccard,
+    /// Euclidean distance of the centred log-ratios, the pseudocount is added to all the counts
+    Aitchison { pseudocount: f64 },
+    /// Euclidean distance of the square roots of the proportions
+    Hellinger,
+}
+
+impl Distance {
+    /// Distance between two samples, two samples without reads are at a distance of zero
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn between(self, a: &[u64], b: &[u64]) -> f64 {
+        match self {
+            Self::BrayCurtis => {
+                let (difference, sum) =
+                    a.iter().zip(b).fold((0, 0), |(difference, sum), (a, b)| {
+                        (difference + a.max(b) - a.min(b), sum + a + b)
+                    });
+
+                if sum == 0 {
+                    0.0
+                } else {
+                    difference as f64 / sum as f64
+                }
+            }
+            Self::Jaccard => {
+                let (shared, union) =
+                    a.iter().zip(b).fold((0, 0), |(shared, union), (a, b)| {
+                        match (*a > 0, *b > 0) {
+                            (true, true) => (shared + 1, union + 1),
+                            (true, false) | (false, true) => (shared, union + 1),
+                            (false, false) => (shared, union),
+                        }
+                    });
+
+                if union == 0 {
+                    0.0
+                } else {
+                    1.0 - f64::from(shared) / f64::from(union)
+                }
+            }
+            Self::Aitchison { pseudocount } => {
+                euclidean(&clr(a, pseudocount), &clr(b, pseudocount))
+            }
+            Self::Hellinger => euclidean(&hellinger(a), &hellinger(b)),
+        }
+    }
+}
+
+/// Centred log-ratio transformation
+#[allow(clippy::cast_precision_loss)]
+fn clr(counts: &[u64], pseudocount: f64) -> Vec<f64> {
+    let logs: Vec<f64> = counts
+        .iter()
+        .map(|count| (*count as f64 + pseudocount).ln())
+        .collect();
+    let mean = logs.iter().sum::<f64>() / logs.len().max(1) as f64;
+
+    logs.into_iter().map(|log| log - mean).collect()
+}
+
+/// Square roots of the proportions
+#[allow(clippy::cast_precision_loss)]
+fn hellinger(counts: &[u64]) -> Vec<f64> {
+    let total: u64 = counts.iter().sum();
+
+    counts
+        .iter()
+        .map(|count| {
+            if total == 0 {
+                0.0
+            } else {
+                (*count as f64 / total as f64).sqrt()
+            }
+        })
+        .collect()
+}
+
+fn euclidean(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Square matrix of the distances between all the samples, the distance is computed once for each pair
+pub fn distance_matrix<T, F>(samples: &[T], distance: F) -> Vec<Vec<f64>>
+where
+    F: Fn(&T, &T) -> f64,
+{
+    let mut matrix = vec![vec![0.0; samples.len()]; samples.len()];
+
+    for (i, a) in samples.iter().enumerate() {
+        for (j, b) in samples.iter().enumerate().skip(i + 1) {
+            let value = distance(a, b);
+            matrix[i][j] = value;
+            matrix[j][i] = value;
+        }
+    }
+
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(Distance::BrayCurtis => 0.6)]
+    #[test_case(Distance::Jaccard => 0.666_667)]
+    #[test_case(Distance::Aitchison { pseudocount: 1.0 } => 2.751_933)]
+    #[test_case(Distance::Hellinger => 1.095_445)]
+    fn test_distance(distance: Distance) -> f64 {
+        let value = distance.between(&[6, 4, 0], &[0, 4, 6]);
+        (value * 1e6).round() / 1e6
+    }
+
+    #[test_case(Distance::BrayCurtis)]
+    #[test_case(Distance::Jaccard)]
+    #[test_case(Distance::Hellinger)]
+    fn test_distance_empty_samples(distance: Distance) {
+        pretty_assertions::assert_eq!(distance.between(&[0, 0], &[0, 0]), 0.0);
+    }
+
+    #[test]
+    fn test_distance_matrix() {
+        let matrix = distance_matrix(&[vec![1, 1], vec![1, 0], vec![1, 1]], |a, b| {
+            Distance::Jaccard.between(a, b)
+        });
+
+        pretty_assertions::assert_eq!(
+            matrix,
+            vec![
+                vec![0.0, 0.5, 0.0],
+                vec![0.5, 0.0, 0.5],
+                vec![0.0, 0.5, 0.0]
+            ]
+        );
+    }
+}
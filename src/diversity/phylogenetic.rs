@@ -0,0 +1,138 @@
+use std::collections::{HashMap, HashSet};
+
+use super::classified_nodes;
+use crate::{
+    data::tree::{IndentedTaxon, Tree},
+    errors::SpideogError,
+};
+
+/// Diversity indices computed on the taxonomy tree of a sample, from the read counts of the taxa
+/// at a rank. The path length between two taxa goes through their lowest common ancestor.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TreeDiversity {
+    /// Faith's phylogenetic diversity, total length of the branches from the root to the taxa with
+    /// reads
+    pub faith_pd: f64,
+    /// Clarke & Warwick taxonomic diversity (Δ), `sum(w x x) / (N (N - 1) / 2)` over the pairs of
+    /// taxa, with `w` their path length, `x` their reads and `N` the total of the reads, not defined
+    /// with less than two reads
+    pub taxonomic_diversity: Option<f64>,
+    /// Clarke & Warwick taxonomic distinctness (Δ*), `sum(w x x) / sum(x x)` over the pairs of taxa,
+    /// not defined with less than two taxa
+    pub taxonomic_distinctness: Option<f64>,
+}
+
+impl TreeDiversity {
+    /// The counts are the reads of the taxa by taxid, the taxa not found in the tree are left out.
+    /// The length of the branch between a node and its parent is given by `length(node, parent)`.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn new<F, E>(tree: &Tree, counts: &HashMap<u64, u64>, mut length: F) -> Result<Self, E>
+    where
+        F: FnMut(&IndentedTaxon, &IndentedTaxon) -> Result<f64, E>,
+        E: From<SpideogError>,
+    {
+        let nodes = classified_nodes(tree)?;
+
+        let mut branches = vec![0.0; nodes.len()];
+        let mut depths = vec![0.0; nodes.len()];
+        for (position, (node, parent)) in nodes.iter().enumerate() {
+            if let Some(parent) = parent {
+                branches[position] = length(node, nodes[*parent].0)?;
+                depths[position] = depths[*parent] + branches[position];
+            }
+        }
+
+        let parents: Vec<Option<usize>> = nodes.iter().map(|(_, parent)| *parent).collect();
+        let parents = &parents;
+        let ancestors = move |position: usize| {
+            std::iter::successors(Some(position), move |position| parents[*position])
+        };
+
+        let observed: Vec<(usize, f64)> = nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(position, (node, _))| {
+                counts
+                    .get(&node.taxon.taxonomy_id)
+                    .filter(|count| **count > 0)
+                    .map(|count| (position, *count as f64))
+            })
+            .collect();
+
+        let spanned: HashSet<usize> = observed
+            .iter()
+            .flat_map(|(position, _)| ancestors(*position))
+            .collect();
+        let faith_pd = spanned.iter().map(|position| branches[*position]).sum();
+
+        let mut weighted = 0.0;
+        let mut pairs = 0.0;
+        for (i, (a, reads_a)) in observed.iter().enumerate() {
+            let lineage: HashSet<usize> = ancestors(*a).collect();
+
+            for (b, reads_b) in &observed[i + 1..] {
+                let common = ancestors(*b)
+                    .find(|ancestor| lineage.contains(ancestor))
+                    .map_or(0.0, |ancestor| depths[ancestor]);
+                let path_length = depths[*a] + depths[*b] - 2.0 * common;
+
+                weighted += path_length * reads_a * reads_b;
+                pairs += reads_a * reads_b;
+            }
+        }
+
+        let total: f64 = observed.iter().map(|(_, reads)| reads).sum();
+
+        Ok(Self {
+            faith_pd,
+            taxonomic_diversity: if total > 1.0 {
+                Some(weighted / (total * (total - 1.0) / 2.0))
+            } else {
+                None
+            },
+            taxonomic_distinctness: if pairs > 0.0 {
+                Some(weighted / pairs)
+            } else {
+                None
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::fixtures::tree;
+    use test_case::test_case;
+
+    fn tree_diversity(counts: &[(u64, u64)]) -> TreeDiversity {
+        let counts = counts.iter().copied().collect();
+        TreeDiversity::new::<_, SpideogError>(&tree(), &counts, |node, parent| {
+            #[allow(clippy::cast_precision_loss)]
+            Ok((node.indent - parent.indent) as f64)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_tree_diversity() {
+        let diversity = tree_diversity(&[(562, 2), (208_962, 1), (1280, 1), (9606, 3)]);
+
+        pretty_assertions::assert_eq!(diversity.faith_pd, 6.0);
+        pretty_assertions::assert_eq!(diversity.taxonomic_diversity, Some(19.0 / 6.0));
+        pretty_assertions::assert_eq!(diversity.taxonomic_distinctness, Some(19.0 / 5.0));
+    }
+
+    #[test_case(&[(562, 5)] => (2.0, Some(0.0), None) ; "one taxon")]
+    #[test_case(&[(562, 1), (1280, 0)] => (2.0, None, None) ; "one read")]
+    #[test_case(&[] => (0.0, None, None) ; "no reads")]
+    fn test_tree_diversity_undefined(counts: &[(u64, u64)]) -> (f64, Option<f64>, Option<f64>) {
+        let diversity = tree_diversity(counts);
+
+        (
+            diversity.faith_pd,
+            diversity.taxonomic_diversity,
+            diversity.taxonomic_distinctness,
+        )
+    }
+}
@@ -0,0 +1,273 @@
+use daggy::NodeIndex;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+
+use crate::{
+    data::tree::{Reads, Tree},
+    errors::SpideogError,
+    kraken::ReportRecord,
+    taxonomy::Rank,
+};
+
+/// Draw `depth` reads without replacement from the read counts (all of them when there are fewer
+/// reads), each read is kept with the probability of the draws left over the reads left
+pub fn subsample<R: Rng>(counts: &[u64], depth: u64, rng: &mut R) -> Vec<u64> {
+    let mut remaining: u64 = counts.iter().sum();
+    let mut draws = depth.min(remaining);
+
+    counts
+        .iter()
+        .map(|count| {
+            let mut kept = 0;
+            let mut left = *count;
+
+            while left > 0 && draws > 0 && draws < remaining {
+                if rng.gen_range(0..remaining) < draws {
+                    kept += 1;
+                    draws -= 1;
+                }
+                remaining -= 1;
+                left -= 1;
+            }
+
+            // all the reads left are drawn
+            if draws > 0 && draws == remaining {
+                kept += left;
+                draws -= left;
+            }
+            remaining -= left;
+
+            kept
+        })
+        .collect()
+}
+
+/// Nodes of a tree in depth-first order with the position of their parent
+fn nodes_in_order(tree: &Tree) -> Result<Vec<(NodeIndex, Option<usize>)>, SpideogError> {
+    let origin = tree.origin.ok_or(SpideogError::TreeNotInitialized)?;
+
+    let mut nodes = Vec::new();
+    let mut stack = vec![(origin, None)];
+    while let Some((index, parent)) = stack.pop() {
+        nodes.push((index, parent));
+        let position = nodes.len() - 1;
+        for child in tree.children_of(index).into_iter().rev() {
+            stack.push((child, Some(position)));
+        }
+    }
+
+    Ok(nodes)
+}
+
+fn is_unclassified(tree: &Tree, index: NodeIndex) -> bool {
+    tree.tree
+        .node_weight(index)
+        .is_some_and(|node| matches!(node.taxon.taxonomy_level, Rank::Unclassified(_)))
+}
+
+/// Subsample the reads assigned directly to the taxa of a report tree (the taxon reads, including
+/// the unclassified reads) to a depth, the clade reads are rebuilt from them. The subsampling is
+/// reproducible for a given seed.
+pub fn rarefy(tree: &Tree, depth: u64, seed: u64) -> Result<Tree, SpideogError> {
+    let nodes = nodes_in_order(tree)?;
+    let counts: Vec<u64> = nodes
+        .iter()
+        .map(|(index, _)| {
+            tree.tree
+                .node_weight(*index)
+                .and_then(|node| node.reads)
+                .map_or(0, |reads| reads.taxon)
+        })
+        .collect();
+
+    let total: u64 = counts.iter().sum();
+    if total < depth {
+        return Err(SpideogError::RarefactionDepth(total, depth));
+    }
+
+    let mut rng = Pcg64::seed_from_u64(seed);
+    let taxon_reads = subsample(&counts, depth, &mut rng);
+
+    // the unclassified node is the parent of the root in the trees but not its clade
+    let mut clade_reads = taxon_reads.clone();
+    for (position, (index, parent)) in nodes.iter().enumerate().rev() {
+        if let Some(parent) = parent {
+            if !is_unclassified(tree, nodes[*parent].0) && !is_unclassified(tree, *index) {
+                clade_reads[*parent] += clade_reads[position];
+            }
+        }
+    }
+
+    let mut rarefied = tree.clone();
+    for (position, (index, _)) in nodes.iter().enumerate() {
+        let node = rarefied
+            .tree
+            .node_weight_mut(*index)
+            .ok_or(SpideogError::NodeNotFound)?;
+        node.reads = Some(Reads {
+            clade: clade_reads[position],
+            taxon: taxon_reads[position],
+        });
+    }
+
+    Ok(rarefied)
+}
+
+/// Lines of a Kraken report from a tree, in depth-first order and indented as in the tree. The
+/// taxa without reads in their clade are left out, as Kraken2 does by default.
+#[allow(clippy::cast_precision_loss)]
+pub fn report_records(tree: &Tree) -> Result<Vec<ReportRecord>, SpideogError> {
+    let nodes = nodes_in_order(tree)?;
+    let nodes = nodes
+        .iter()
+        .map(|(index, _)| {
+            tree.tree
+                .node_weight(*index)
+                .ok_or(SpideogError::NodeNotFound)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let total: u64 = nodes
+        .iter()
+        .filter_map(|node| node.reads)
+        .map(|reads| reads.taxon)
+        .sum();
+    let percentage = |reads: u64| {
+        if total == 0 {
+            String::from("0.00")
+        } else {
+            format!("{:.2}", reads as f64 * 100.0 / total as f64)
+        }
+    };
+
+    Ok(nodes
+        .into_iter()
+        .filter_map(|node| {
+            let reads = node.reads.filter(|reads| reads.clade > 0)?;
+
+            Some((
+                percentage(reads.clade),
+                reads.clade,
+                reads.taxon,
+                node.taxon.taxonomy_level,
+                node.taxon.taxonomy_id,
+                format!("{}{}", "  ".repeat(node.indent), node.taxon.name),
+            ))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::fixtures::tree;
+    use test_case::test_case;
+
+    #[test_case(&[5, 0, 3, 2], 4)]
+    #[test_case(&[5, 0, 3, 2], 10 ; "all the reads")]
+    #[test_case(&[5, 0, 3, 2], 20 ; "more than the reads")]
+    #[test_case(&[5, 0, 3, 2], 0 ; "no reads")]
+    fn test_subsample(counts: &[u64], depth: u64) {
+        let mut rng = Pcg64::seed_from_u64(42);
+        let subsampled = subsample(counts, depth, &mut rng);
+
+        pretty_assertions::assert_eq!(
+            subsampled.iter().sum::<u64>(),
+            depth.min(counts.iter().sum())
+        );
+        assert!(subsampled
+            .iter()
+            .zip(counts)
+            .all(|(subsampled, count)| subsampled <= count));
+    }
+
+    #[test]
+    fn test_rarefy() {
+        let rarefied = rarefy(&tree(), 50, 1).unwrap();
+        let records = report_records(&rarefied).unwrap();
+
+        let taxon_reads: u64 = records.iter().map(|record| record.2).sum();
+        pretty_assertions::assert_eq!(taxon_reads, 50);
+
+        let clade = |taxonomy_id| {
+            records
+                .iter()
+                .find(|record| record.4 == taxonomy_id)
+                .map_or(0, |record| record.1)
+        };
+        let taxon = |taxonomy_id| {
+            records
+                .iter()
+                .find(|record| record.4 == taxonomy_id)
+                .map_or(0, |record| record.2)
+        };
+        pretty_assertions::assert_eq!(clade(0), taxon(0));
+        pretty_assertions::assert_eq!(clade(1), 50 - taxon(0));
+        pretty_assertions::assert_eq!(clade(561), taxon(561) + taxon(562));
+
+        // same seed, same subsample
+        pretty_assertions::assert_eq!(
+            records,
+            report_records(&rarefy(&tree(), 50, 1).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rarefy_too_deep() {
+        assert!(matches!(
+            rarefy(&tree(), 101, 1),
+            Err(SpideogError::RarefactionDepth(100, 101))
+        ));
+    }
+
+    #[test]
+    fn test_report_records() {
+        let records = report_records(&rarefy(&tree(), 100, 1).unwrap()).unwrap();
+
+        pretty_assertions::assert_eq!(
+            records,
+            vec![
+                (
+                    "40.00".to_string(),
+                    40,
+                    40,
+                    Rank::Unclassified(0),
+                    0,
+                    "unclassified".to_string()
+                ),
+                (
+                    "60.00".to_string(),
+                    60,
+                    5,
+                    Rank::Root(0),
+                    1,
+                    "root".to_string()
+                ),
+                (
+                    "45.00".to_string(),
+                    45,
+                    15,
+                    Rank::Genus(0),
+                    561,
+                    "  Escherichia".to_string()
+                ),
+                (
+                    "30.00".to_string(),
+                    30,
+                    30,
+                    Rank::Species(0),
+                    562,
+                    "    Escherichia coli".to_string()
+                ),
+                (
+                    "10.00".to_string(),
+                    10,
+                    10,
+                    Rank::Species(0),
+                    1280,
+                    "      Staphylococcus aureus".to_string()
+                ),
+            ]
+        );
+    }
+}
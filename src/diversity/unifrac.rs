@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use super::classified_nodes;
+use crate::{
+    data::tree::{IndentedTaxon, Tree},
+    errors::SpideogError,
+};
+
+/// UniFrac distance between two samples placed on the branches of a tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UniFrac {
+    /// Length of the branches leading to the reads of only one of the samples, over the length of
+    /// the branches leading to the reads of any of the samples
+    Unweighted,
+    /// Normalized weighted UniFrac, `sum(l |pa - pb|) / sum(l (pa + pb))` with `pa` and `pb` the
+    /// proportions of the reads of the samples below each branch of length `l`
+    Weighted,
+}
+
+impl UniFrac {
+    /// Distance between two samples, the proportions are given for each branch
+    #[must_use]
+    pub fn between(self, lengths: &[f64], a: &[f64], b: &[f64]) -> f64 {
+        let (difference, sum) = lengths.iter().zip(a.iter().zip(b)).fold(
+            (0.0, 0.0),
+            |(difference, sum), (length, (a, b))| match self {
+                Self::Unweighted => match (*a > 0.0, *b > 0.0) {
+                    (true, true) => (difference, sum + length),
+                    (true, false) | (false, true) => (difference + length, sum + length),
+                    (false, false) => (difference, sum),
+                },
+                Self::Weighted => (difference + length * (a - b).abs(), sum + length * (a + b)),
+            },
+        );
+
+        if sum > 0.0 {
+            difference / sum
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Branches of a tree, with the proportion of the reads of each sample in the clade below each
+/// branch. The unclassified node and its reads are left out, so the branches start from the root.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Branches {
+    pub lengths: Vec<f64>,
+    /// Proportions of the reads of each sample (rows) below each branch (columns)
+    pub proportions: Vec<Vec<f64>>,
+}
+
+impl Branches {
+    /// Place the samples on a tree, usually the combination of their trees. The reads of each
+    /// sample are the reads assigned directly to each taxon (the taxon reads) by taxid, the clade
+    /// reads are summed from them. The length of the branch between a node and its parent is given
+    /// by `length(node, parent)`.
+    pub fn new<F, E>(
+        tree: &Tree,
+        taxon_reads: &[HashMap<u64, u64>],
+        mut length: F,
+    ) -> Result<Self, E>
+    where
+        F: FnMut(&IndentedTaxon, &IndentedTaxon) -> Result<f64, E>,
+        E: From<SpideogError>,
+    {
+        let nodes = classified_nodes(tree)?;
+
+        let mut clades = vec![vec![0_u64; nodes.len()]; taxon_reads.len()];
+        let mut totals = vec![0_u64; taxon_reads.len()];
+        for (position, (node, parent)) in nodes.iter().enumerate().rev() {
+            for (sample, reads) in taxon_reads.iter().enumerate() {
+                let own = reads.get(&node.taxon.taxonomy_id).copied().unwrap_or(0);
+                clades[sample][position] += own;
+                totals[sample] += own;
+                if let Some(parent) = parent {
+                    clades[sample][*parent] += clades[sample][position];
+                }
+            }
+        }
+
+        let mut branches = Self {
+            lengths: Vec::new(),
+            proportions: vec![Vec::new(); taxon_reads.len()],
+        };
+        for (position, (node, parent)) in nodes.iter().enumerate() {
+            let parent = match parent {
+                Some(parent) => nodes[*parent].0,
+                None => continue,
+            };
+
+            branches.lengths.push(length(node, parent)?);
+            for (sample, total) in totals.iter().enumerate() {
+                branches.proportions[sample].push(proportion(clades[sample][position], *total));
+            }
+        }
+
+        Ok(branches)
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn proportion(reads: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        reads as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{data::fixtures::tree, diversity::beta::distance_matrix};
+    use test_case::test_case;
+
+    fn samples() -> Vec<HashMap<u64, u64>> {
+        vec![
+            vec![(0, 10), (562, 1), (208_962, 1)].into_iter().collect(),
+            vec![(1280, 2)].into_iter().collect(),
+            vec![(1, 4), (562, 1), (1280, 1)].into_iter().collect(),
+        ]
+    }
+
+    fn branches() -> Branches {
+        Branches::new::<_, SpideogError>(&tree(), &samples(), |node, parent| {
+            #[allow(clippy::cast_precision_loss)]
+            Ok((node.indent - parent.indent) as f64)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_branches() {
+        let branches = branches();
+
+        pretty_assertions::assert_eq!(branches.lengths, vec![1.0, 1.0, 1.0, 3.0]);
+        pretty_assertions::assert_eq!(
+            branches.proportions,
+            vec![
+                vec![1.0, 0.5, 0.5, 0.0],
+                vec![0.0, 0.0, 0.0, 1.0],
+                vec![1.0 / 6.0, 1.0 / 6.0, 0.0, 1.0 / 6.0]
+            ]
+        );
+    }
+
+    #[test_case(UniFrac::Unweighted, 0, 1 => 1.0)]
+    #[test_case(UniFrac::Unweighted, 0, 2 => 0.666_667)]
+    #[test_case(UniFrac::Weighted, 0, 1 => 1.0)]
+    #[test_case(UniFrac::Weighted, 0, 2 => 0.764_706)]
+    #[test_case(UniFrac::Weighted, 1, 1 => 0.0)]
+    fn test_unifrac(unifrac: UniFrac, a: usize, b: usize) -> f64 {
+        let branches = branches();
+        let matrix = distance_matrix(&branches.proportions, |a, b| {
+            unifrac.between(&branches.lengths, a, b)
+        });
+
+        (matrix[a][b] * 1e6).round() / 1e6
+    }
+
+    #[test]
+    fn test_unifrac_empty_samples() {
+        pretty_assertions::assert_eq!(
+            UniFrac::Weighted.between(&[1.0, 2.0], &[0.0, 0.0], &[0.0, 0.0]),
+            0.0
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{data::tree::IndentedTaxon, taxonomy::Rank};
+use crate::{kraken::Taxon, taxonomy::Rank};
 use displaydoc::Display;
 use thiserror::Error;
 
@@ -16,17 +16,37 @@ pub enum SpideogError {
     /// node not found
     NodeNotFound,
     /// edge between `{0}` and `{1}` not found
-    EdgeNotFound(IndentedTaxon, IndentedTaxon),
+    EdgeNotFound(Taxon, Taxon),
     /// parse output error
     ParseOutputPathError,
     /// input file is empty
     EmptyFile,
     /// Kraken parser error
     KrakenParser(#[source] csv::Error),
+    /// expected 6 or 8 columns in Kraken report (with minimizer data), found `{0}`
+    KrakenColumnCount(usize),
+    /// Bracken parser error
+    BrackenParser(#[source] csv::Error),
+    /// failed to parse Kraken read classification at line `{0}`
+    KrakenReadParsing(usize),
+    /// failed to parse MetaPhlAn profile line `{0}`
+    MetaphlanLineParsing(String),
+    /// failed to parse MetaPhlAn clade `{0}`, expected a rank prefix such as `s__`
+    MetaphlanCladeParsing(String),
+    /// the MetaPhlAn profile has no estimated read counts, run MetaPhlAn with `-t rel_ab_w_read_stats`
+    MetaphlanWithoutReads,
+    /// failed to read taxonomy
+    TaxonomyIo(#[source] std::io::Error),
+    /// failed to parse taxonomy line `{0}`
+    TaxonomyLineParsing(usize),
+    /// taxonomy id `{0}` not found in the taxonomy
+    UnknownTaxonomyId(u64),
     /// taxonomy tree is not initialized
     TreeNotInitialized,
     /// failed to parse taxon name and identation
     KrakenIndentParsing,
+    /// the report has `{0}` reads, fewer than the rarefaction depth `{1}`
+    RarefactionDepth(u64, u64),
     /// other
     Other,
 }
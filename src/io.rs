@@ -1,92 +1,398 @@
 use atty::Stream;
+use bzip2::read::MultiBzDecoder;
 use color_eyre::{Help, Report};
 use csv::Reader;
 use dialoguer::Confirm;
+use eyre::Context;
+use flate2::read::MultiGzDecoder;
+use libspideog::{
+    bracken::BrackenData,
+    data::{abundance::SampleName, tree::Tree},
+    diversity::CountedReads,
+    taxonomy::{index::TaxonomyIndex, Rank},
+};
+use std::io::Read;
 use std::process;
-use std::{fs::File, fs::OpenOptions, io, path::PathBuf};
-use tracing::instrument;
+use std::{
+    fs::OpenOptions,
+    io,
+    path::{Path, PathBuf},
+};
+use tracing::{info, instrument, warn};
 
 use crate::{
-    cli::args::{MultipleReports, SingleReport},
+    cli::args::{MultipleReports, OutputPhylo, SingleReport},
     BinError,
 };
 
+use self::{
+    bracken::ParseBrackenReport,
+    detect::detect_format,
+    krakenuniq::ParseKrakenUniqReport,
+    metaphlan::ParseMetaphlanProfile,
+    reads::ParseKrakenReads,
+    report::FromKrakenRecords,
+    report::ParseKrakenReport,
+    taxonomy::{load_taxonomy, require_taxonomy, Enrich},
+};
+
 pub mod abundance_csv;
+pub mod biom;
+pub mod bracken;
+pub mod detect;
+pub mod distance;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod json;
+pub mod krakenuniq;
+pub mod krona;
+pub mod metaphlan;
+pub mod mpa;
 pub mod newick;
+pub mod phyloseq;
+pub mod phyloxml;
+pub mod reads;
 pub mod report;
+pub mod taxonomy;
+pub mod xml;
 
 /* ---------------------------------- Input --------------------------------- */
 
 custom_derive! {
-    #[derive(clap::Clap, Debug, PartialEq)]
+    #[derive(clap::Clap, Debug, PartialEq, Clone, Copy)]
     #[derive(EnumFromStr, EnumDisplay)]
     pub enum InputReportFormat {
         Kraken,
+        Bracken,
+        KrakenReads,
+        KrakenUniq,
+        Metaphlan,
     }
 }
 
-#[instrument]
-pub fn get_reader(input: &PathBuf, headers: bool) -> Result<Reader<File>, csv::Error> {
+impl InputReportFormat {
+    /// Formats always starting with a header line
+    pub fn has_headers(&self) -> bool {
+        matches!(self, Self::Bracken | Self::KrakenUniq)
+    }
+}
+
+/// Report content, decompressed on the fly if needed
+pub type ReportReader = Box<dyn io::Read>;
+
+/// Compression of an input report, detected from its first bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+    Zstd,
+}
+
+impl Compression {
+    const MAGIC_LEN: usize = 4;
+
+    pub fn detect(magic: &[u8]) -> Self {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Self::Gzip
+        } else if magic.starts_with(b"BZh") {
+            Self::Bzip2
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+}
+
+/// Sample name from a report path, ignoring the compression extension (`sample.kreport.gz` is `sample`)
+pub fn sample_name(path: &Path) -> String {
+    if is_stdin(path) {
+        return String::from("stdin");
+    }
+
+    let path = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gz") | Some("bz2") | Some("zst") => path.with_extension(""),
+        _ => path.to_path_buf(),
+    };
+
+    path.file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into())
+}
+
+/// Fill the buffer from the reader, stops early only at the end of the stream
+fn read_start<R: io::Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(len)
+}
+
+/// Wrap a reader into a streaming decoder matching its magic bytes
+pub fn decompress<R: io::Read + 'static>(mut reader: R) -> io::Result<ReportReader> {
+    let mut magic = [0_u8; Compression::MAGIC_LEN];
+    let len = read_start(&mut reader, &mut magic)?;
+
+    // put the magic bytes back in front of the stream
+    let reader = io::Cursor::new(magic[..len].to_vec()).chain(reader);
+
+    Ok(match Compression::detect(&magic[..len]) {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::Decoder::new(reader)?),
+    })
+}
+
+/// CSV reader used by all the subcommands to read reports
+pub fn report_reader(reader: ReportReader, headers: bool) -> Reader<ReportReader> {
     csv::ReaderBuilder::new()
         .has_headers(headers)
         .delimiter(b'\t')
         .double_quote(false)
+        .comment(Some(b'#'))
         .flexible(true)
-        .from_path(input)
+        .from_reader(reader)
 }
 
-impl SingleReport {
-    #[instrument]
-    pub fn open_report(&self) -> Result<File, BinError> {
-        let path = &self.path;
-        open_file(path)
+/// Number of bytes read from the start of a report to detect its format
+const SNIFF_LEN: usize = 16 * 1024;
+
+/// Read the start of a (decompressed) report without consuming it
+pub fn sniff(mut reader: ReportReader) -> io::Result<(String, ReportReader)> {
+    let mut start = vec![0_u8; SNIFF_LEN];
+    let len = read_start(&mut reader, &mut start)?;
+    start.truncate(len);
+
+    let mut text = String::from_utf8_lossy(&start).into_owned();
+    if len == SNIFF_LEN {
+        // the last line is probably cut
+        text.truncate(text.rfind('\n').unwrap_or(0));
     }
+
+    Ok((text, Box::new(io::Cursor::new(start).chain(reader))))
 }
 
-impl MultipleReports {
-    fn join_errors(errors: Vec<Result<File, BinError>>) -> Result<(), Report> {
-        if errors.is_empty() {
-            return Ok(());
-        }
+/// Opened report, with its format given on the command line or detected from its content
+pub struct ReportInput {
+    pub path: PathBuf,
+    pub format: InputReportFormat,
+    pub reader: Reader<ReportReader>,
+}
 
-        errors
-            .into_iter()
-            .filter_map(|result| {
-                if let Err(error) = result {
-                    Some(error)
-                } else {
-                    None
-                }
+impl ReportInput {
+    #[instrument(skip(reader))]
+    pub fn new(
+        path: &PathBuf,
+        reader: ReportReader,
+        format: Option<InputReportFormat>,
+        headers: bool,
+    ) -> Result<Self, BinError> {
+        let (start, reader) = sniff(reader).map_err(|err| BinError::Io {
+            err,
+            path: path.clone(),
+        })?;
+        let detected = detect_format(&start);
+
+        let format = match (format, detected) {
+            (Some(format), _) => format,
+            (None, Some(detected)) => {
+                info!(
+                    "detected format `{}` for `{}`",
+                    detected.format,
+                    path.display()
+                );
+                detected.format
+            }
+            (None, None) => {
+                warn!(
+                    "could not detect the format of `{}`, reading it as a Kraken report",
+                    path.display()
+                );
+                InputReportFormat::Kraken
+            }
+        };
+
+        // headers given on the command line, always present in the format, or detected
+        let headers = headers
+            || format.has_headers()
+            || detected.is_some_and(|detected| detected.format == format && detected.headers);
+
+        Ok(Self {
+            path: path.clone(),
+            format,
+            reader: report_reader(reader, headers),
+        })
+    }
+
+    /// Build a tree or an abundance dataset from any report format but Bracken
+    pub fn parse<T: FromKrakenRecords + Enrich>(
+        &mut self,
+        taxonomy: Option<&TaxonomyIndex>,
+    ) -> Result<T, BinError> {
+        let parsed = match (self.format, taxonomy) {
+            (InputReportFormat::Kraken, _) => ParseKrakenReport::parse(&mut self.reader),
+            (InputReportFormat::KrakenReads, Some(taxonomy)) => {
+                ParseKrakenReads::parse_reads(&mut self.reader, taxonomy)
+            }
+            (InputReportFormat::KrakenReads, None) => {
+                return Err(BinError::MissingTaxonomy(self.path.clone()))
+            }
+            (InputReportFormat::KrakenUniq, _) => {
+                ParseKrakenUniqReport::parse_krakenuniq(&mut self.reader)
+            }
+            (InputReportFormat::Metaphlan, _) => {
+                ParseMetaphlanProfile::parse_metaphlan(&mut self.reader)
+            }
+            (InputReportFormat::Bracken, _) => {
+                return Err(BinError::BrackenTree(self.path.clone()))
+            }
+        };
+
+        parsed
+            .and_then(|data: T| match taxonomy {
+                Some(taxonomy) => data.enrich(taxonomy),
+                None => Ok(data),
+            })
+            .map_err(|err| BinError::Parse {
+                err,
+                path: self.path.clone(),
+            })
+    }
+
+    /// Read a Bracken abundance table
+    pub fn parse_bracken(
+        &mut self,
+        taxonomy: Option<&TaxonomyIndex>,
+    ) -> Result<BrackenData, BinError> {
+        ParseBrackenReport::parse(&mut self.reader)
+            .and_then(|data: BrackenData| match taxonomy {
+                Some(taxonomy) => data.enrich(taxonomy),
+                None => Ok(data),
             })
-            .fold(Err(eyre!("encountered multiple errors")), |report, e| {
-                report.error(e)
+            .map_err(|err| BinError::Parse {
+                err,
+                path: self.path.clone(),
             })
     }
+}
 
+impl SingleReport {
     #[instrument]
-    pub fn open_reports(&self) -> Result<Vec<File>, Report> {
-        let readers: Vec<Result<File, BinError>> =
-            self.paths.iter().map(|p| open_file(p)).collect();
+    pub fn open_report(&self) -> Result<ReportInput, BinError> {
+        let reader = open_file(&self.path)?;
+
+        ReportInput::new(&self.path, reader, self.format, self.headers)
+    }
+
+    /// Load the taxonomy given with `--taxonomy`, required for per-read classifications
+    pub fn taxonomy_for(&self, format: InputReportFormat) -> Result<Option<TaxonomyIndex>, Report> {
+        match (&self.taxonomy, format) {
+            (Some(path), _) => load_taxonomy(path).map(Some),
+            (None, InputReportFormat::KrakenReads) => require_taxonomy(&self.taxonomy).map(Some),
+            (None, _) => Ok(None),
+        }
+    }
+}
 
-        let (ok, errors) = readers.into_iter().partition(Result::is_ok);
+/// Fail on the Bracken abundance tables, for the subcommands that need a taxonomy tree
+pub fn reject_bracken(inputs: &[ReportInput]) -> Result<(), Report> {
+    match inputs
+        .iter()
+        .find(|input| input.format == InputReportFormat::Bracken)
+    {
+        Some(input) => Err(eyre!(
+            "`{}` is a Bracken abundance table, it does not contain a taxonomy tree",
+            input.path.display()
+        ))
+        .suggestion("use the Kraken report given to Bracken instead"),
+        None => Ok(()),
+    }
+}
 
-        Self::join_errors(errors)?;
+/// Collect the errors of multiple results into a single report
+pub fn join_errors<T, E>(results: Vec<Result<T, E>>) -> Result<(), Report>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    if results.is_empty() {
+        return Ok(());
+    }
 
-        Ok(ok.into_iter().map(Result::unwrap).collect::<Vec<File>>())
+    let mut report = eyre!("encountered multiple errors");
+    for error in results.into_iter().filter_map(Result::err) {
+        report = report.error(error);
     }
+
+    Err(report)
+}
+
+impl MultipleReports {
+    #[instrument]
+    pub fn open_reports(&self) -> Result<Vec<ReportInput>, Report> {
+        if self.paths.iter().filter(|path| is_stdin(path)).count() > 1 {
+            return Err(eyre!("the standard input `-` can only be read once"))
+                .suggestion("give the other reports as file paths");
+        }
+
+        let inputs: Vec<Result<ReportInput, BinError>> = self
+            .paths
+            .iter()
+            .map(|path| ReportInput::new(path, open_file(path)?, self.format, self.headers))
+            .collect();
+
+        let (ok, errors) = inputs.into_iter().partition(Result::is_ok);
+
+        join_errors(errors)?;
+
+        Ok(ok.into_iter().map(Result::unwrap).collect())
+    }
+
+    /// Load the taxonomy given with `--taxonomy`, required if one of the inputs has per-read classifications
+    pub fn taxonomy_for(&self, inputs: &[ReportInput]) -> Result<Option<TaxonomyIndex>, Report> {
+        if let Some(path) = &self.taxonomy {
+            load_taxonomy(path).map(Some)
+        } else if inputs
+            .iter()
+            .any(|input| input.format == InputReportFormat::KrakenReads)
+        {
+            require_taxonomy(&self.taxonomy).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Input path standing for the standard input
+pub const STDIN: &str = "-";
+
+pub fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == STDIN
 }
 
 #[instrument]
-pub fn open_file(path: &PathBuf) -> Result<File, BinError> {
-    let path = path;
-    OpenOptions::new()
-        .read(true)
-        .write(false)
-        .open(path)
-        .map_err(|err| BinError::Io {
-            err,
-            path: path.clone(),
-        })
+pub fn open_file(path: &PathBuf) -> Result<ReportReader, BinError> {
+    let reader = if is_stdin(path) {
+        decompress(io::stdin())
+    } else {
+        OpenOptions::new()
+            .read(true)
+            .write(false)
+            .open(path)
+            .and_then(decompress)
+    };
+
+    reader.map_err(|err| BinError::Io {
+        err,
+        path: path.clone(),
+    })
 }
 
 /* --------------------------------- OUTPUT --------------------------------- */
@@ -96,14 +402,290 @@ custom_derive! {
     #[derive(EnumFromStr, EnumDisplay)]
     pub enum OutputPhyloFormat {
         Newick,
+        Nhx,
+        Json,
+        Ndjson,
+        Krona,
+        KronaHtml,
+        Mpa,
+        Phyloxml,
+    }
+}
+
+impl OutputPhyloFormat {
+    /// Formats keeping the samples apart, the trees are not combined
+    #[must_use]
+    pub fn per_sample(&self) -> bool {
+        matches!(self, Self::Krona | Self::KronaHtml | Self::Mpa)
     }
 }
 
+/// Write the trees of the samples. The formats writing a single tree (see [`OutputPhyloFormat::per_sample`])
+/// use the combined tree, or the tree of the first sample.
+pub fn write_tree<W: io::Write>(
+    writer: &mut W,
+    samples: &[(SampleName, Tree)],
+    combined: Option<&Tree>,
+    options: &OutputPhylo,
+) -> Result<(), Report> {
+    let tree = || {
+        combined
+            .or_else(|| samples.iter().map(|(_, tree)| tree).next())
+            .ok_or_else(|| eyre!("no tree to write"))
+    };
+    let style = newick::Style {
+        branch_length: options.branch_length,
+        label: options.label,
+    };
+
+    if options.krona_js.is_some() && options.format != OutputPhyloFormat::KronaHtml {
+        return Err(eyre!("`--krona-js` is only used by Krona HTML charts"))
+            .suggestion("use `--format krona-html`, or remove `--krona-js`");
+    }
+
+    match options.format {
+        OutputPhyloFormat::Newick => newick::write_newick(writer, tree()?, &style),
+        OutputPhyloFormat::Nhx => {
+            let nhx =
+                combined.map_or_else(newick::Nhx::default, |_| newick::Nhx::combined(samples));
+            newick::write_nhx(writer, tree()?, &style, &nhx)
+        }
+        OutputPhyloFormat::Json => json::write_json(writer, tree()?),
+        OutputPhyloFormat::Ndjson => json::write_ndjson_tree(writer, tree()?),
+        OutputPhyloFormat::Krona => krona::write_krona_text(writer, &krona_chart(samples)),
+        OutputPhyloFormat::KronaHtml => {
+            let script = options
+                .krona_js
+                .as_ref()
+                .map(std::fs::read_to_string)
+                .transpose()
+                .wrap_err("failed to read the Krona JavaScript")?;
+            krona::write_krona_html(writer, &krona_chart(samples), script.as_deref())
+        }
+        OutputPhyloFormat::Mpa => {
+            mpa::MpaTable::from_trees(samples, options.canonical_ranks).write(writer)
+        }
+        OutputPhyloFormat::Phyloxml => phyloxml::write_phyloxml(writer, tree()?, samples),
+    }
+}
+
+/// Combine the trees of the samples, on the backbone of the taxonomy when there is one, or stitched together
+pub fn combine_trees(
+    samples: &[(SampleName, Tree)],
+    taxonomy: Option<&TaxonomyIndex>,
+) -> Result<Tree, Report> {
+    let mut trees = samples.iter().map(|(_, tree)| tree.clone());
+
+    let combined_tree = match taxonomy {
+        Some(taxonomy) => Tree::backbone(&trees.collect::<Vec<Tree>>(), taxonomy)?,
+        None => trees.try_fold(Tree::new(), Tree::try_combine_with)?,
+    };
+
+    Ok(combined_tree)
+}
+
+fn krona_chart(samples: &[(SampleName, Tree)]) -> krona::KronaChart {
+    krona::KronaChart::from_trees(samples.iter().map(|(name, tree)| (name.as_str(), tree)))
+}
+
 custom_derive! {
     #[derive(clap::Clap, Debug, PartialEq)]
     #[derive(EnumFromStr, EnumDisplay)]
     pub enum OutputAbundanceFormat {
         Csv,
+        Biom,
+        Json,
+        Ndjson,
+        Mpa,
+    }
+}
+
+/// Fail on `--lineage` with another output than CSV, the lineages are only written as CSV columns
+pub fn check_lineage(lineage: bool, format: &OutputAbundanceFormat) -> Result<(), Report> {
+    if lineage && *format != OutputAbundanceFormat::Csv {
+        return Err(eyre!("`--lineage` is only available for the CSV output"))
+            .suggestion("remove `--lineage` or use `--format csv`");
+    }
+
+    Ok(())
+}
+
+custom_derive! {
+    #[derive(clap::Clap, Debug, PartialEq, Clone, Copy)]
+    #[derive(EnumFromStr, EnumDisplay)]
+    pub enum OutputRarefiedFormat {
+        Kraken,
+        Csv,
+        Biom,
+        Json,
+        Ndjson,
+        Mpa,
+    }
+}
+
+impl OutputRarefiedFormat {
+    /// Abundance table format, `None` for Kraken reports
+    #[must_use]
+    pub fn abundance_format(self) -> Option<OutputAbundanceFormat> {
+        match self {
+            Self::Kraken => None,
+            Self::Csv => Some(OutputAbundanceFormat::Csv),
+            Self::Biom => Some(OutputAbundanceFormat::Biom),
+            Self::Json => Some(OutputAbundanceFormat::Json),
+            Self::Ndjson => Some(OutputAbundanceFormat::Ndjson),
+            Self::Mpa => Some(OutputAbundanceFormat::Mpa),
+        }
+    }
+
+    /// Extension of the output files
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Kraken => "kreport",
+            Self::Csv => "csv",
+            Self::Biom => "biom",
+            Self::Json => "json",
+            Self::Ndjson => "ndjson",
+            Self::Mpa => "mpa",
+        }
+    }
+}
+
+custom_derive! {
+    #[derive(clap::Clap, Debug, PartialEq, Clone, Copy)]
+    #[derive(EnumFromStr, EnumDisplay)]
+    pub enum BranchLength {
+        Indent,
+        Rank,
+        One,
+        None,
+    }
+}
+
+custom_derive! {
+    #[derive(clap::Clap, Debug, PartialEq, Clone, Copy)]
+    #[derive(EnumFromStr, EnumDisplay)]
+    pub enum DiversityBranchLength {
+        Indent,
+        Rank,
+        One,
+    }
+}
+
+impl From<DiversityBranchLength> for BranchLength {
+    fn from(branch_length: DiversityBranchLength) -> Self {
+        match branch_length {
+            DiversityBranchLength::Indent => Self::Indent,
+            DiversityBranchLength::Rank => Self::Rank,
+            DiversityBranchLength::One => Self::One,
+        }
+    }
+}
+
+custom_derive! {
+    #[derive(clap::Clap, Debug, PartialEq, Clone, Copy)]
+    #[derive(EnumFromStr, EnumDisplay)]
+    pub enum NewickLabel {
+        Clean,
+        Quoted,
+        Taxid,
+        NameTaxid,
+    }
+}
+
+custom_derive! {
+    #[derive(clap::Clap, Debug, PartialEq, Clone, Copy)]
+    #[derive(EnumFromStr, EnumDisplay)]
+    pub enum AbundanceLayout {
+        Long,
+        Wide,
+    }
+}
+
+custom_derive! {
+    #[derive(clap::Clap, Debug, PartialEq, Clone, Copy)]
+    #[derive(EnumFromStr, EnumDisplay)]
+    pub enum AbundanceValue {
+        CladeReads,
+        TaxonReads,
+        Percentage,
+    }
+}
+
+custom_derive! {
+    #[derive(clap::Clap, Debug, PartialEq, Clone, Copy)]
+    #[derive(EnumFromStr, EnumDisplay)]
+    pub enum CanonicalRank {
+        Domain,
+        Kingdom,
+        Phylum,
+        Class,
+        Order,
+        Family,
+        Genus,
+        Species,
+    }
+}
+
+impl From<CanonicalRank> for Rank {
+    fn from(rank: CanonicalRank) -> Self {
+        match rank {
+            CanonicalRank::Domain => Self::Domain(0),
+            CanonicalRank::Kingdom => Self::Kingdom(0),
+            CanonicalRank::Phylum => Self::Phylum(0),
+            CanonicalRank::Class => Self::Class(0),
+            CanonicalRank::Order => Self::Order(0),
+            CanonicalRank::Family => Self::Family(0),
+            CanonicalRank::Genus => Self::Genus(0),
+            CanonicalRank::Species => Self::Species(0),
+        }
+    }
+}
+
+custom_derive! {
+    #[derive(clap::Clap, Debug, PartialEq, Clone, Copy)]
+    #[derive(EnumFromStr, EnumDisplay)]
+    pub enum DiversityReads {
+        CladeReads,
+        TaxonReads,
+    }
+}
+
+impl From<DiversityReads> for CountedReads {
+    fn from(reads: DiversityReads) -> Self {
+        match reads {
+            DiversityReads::CladeReads => Self::Clade,
+            DiversityReads::TaxonReads => Self::Taxon,
+        }
+    }
+}
+
+custom_derive! {
+    #[derive(clap::Clap, Debug, PartialEq)]
+    #[derive(EnumFromStr, EnumDisplay)]
+    pub enum OutputDiversityFormat {
+        Csv,
+        Json,
+    }
+}
+
+custom_derive! {
+    #[derive(clap::Clap, Debug, PartialEq, Clone, Copy)]
+    #[derive(EnumFromStr, EnumDisplay)]
+    pub enum DistanceMetric {
+        BrayCurtis,
+        Jaccard,
+        Aitchison,
+        Hellinger,
+    }
+}
+
+custom_derive! {
+    #[derive(clap::Clap, Debug, PartialEq)]
+    #[derive(EnumFromStr, EnumDisplay)]
+    pub enum OutputDistanceFormat {
+        Tsv,
+        Phylip,
     }
 }
 
@@ -122,7 +704,7 @@ pub struct Output {
 impl From<Option<PathBuf>> for OutputKind {
     fn from(path: Option<PathBuf>) -> Self {
         path.map_or(Self::Stdout, |p| {
-            if p == PathBuf::from(r"-") {
+            if p.as_os_str() == "-" {
                 Self::Stdout
             } else {
                 Self::File(p)
@@ -185,3 +767,82 @@ impl Output {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use test_case::test_case;
+
+    const REPORT: &str = "100.00\t12\t0\tR\t1\troot\n100.00\t12\t12\tD\t2\t  Bacteria\n";
+
+    fn compress_gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn compress_bzip2(data: &[u8]) -> Vec<u8> {
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn compress_zstd(data: &[u8]) -> Vec<u8> {
+        zstd::encode_all(data, 0).unwrap()
+    }
+
+    fn compress_plain(data: &[u8]) -> Vec<u8> {
+        data.to_vec()
+    }
+
+    #[test_case(compress_plain, Compression::None ; "plain")]
+    #[test_case(compress_gzip, Compression::Gzip ; "gzip")]
+    #[test_case(compress_bzip2, Compression::Bzip2 ; "bzip2")]
+    #[test_case(compress_zstd, Compression::Zstd ; "zstd")]
+    fn test_decompress(compress: fn(&[u8]) -> Vec<u8>, compression: Compression) {
+        let compressed = compress(REPORT.as_bytes());
+        pretty_assertions::assert_eq!(Compression::detect(&compressed), compression);
+
+        let mut content = String::new();
+        decompress(io::Cursor::new(compressed))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+
+        pretty_assertions::assert_eq!(content, REPORT);
+    }
+
+    #[test]
+    fn test_decompress_concatenated_gzip() {
+        let mut compressed = compress_gzip(b"first\n");
+        compressed.extend(compress_gzip(b"second\n"));
+
+        let mut content = String::new();
+        decompress(io::Cursor::new(compressed))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+
+        pretty_assertions::assert_eq!(content, "first\nsecond\n");
+    }
+
+    #[test_case("sample.kreport", "sample" ; "plain")]
+    #[test_case("dir/sample.kreport.gz", "sample" ; "gzip")]
+    #[test_case("sample.zst", "sample" ; "zstd")]
+    #[test_case("-", "stdin" ; "stdin")]
+    fn test_sample_name(path: &str, expected: &str) {
+        pretty_assertions::assert_eq!(sample_name(Path::new(path)), expected);
+    }
+
+    #[test]
+    fn test_decompress_short_input() {
+        let mut content = String::new();
+        decompress(io::Cursor::new(b"1".to_vec()))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+
+        pretty_assertions::assert_eq!(content, "1");
+    }
+}
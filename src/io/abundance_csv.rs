@@ -1,23 +1,137 @@
 use color_eyre::Report;
 use csv::Writer;
 use eyre::Context;
-use libspideog::data::abundance::{AbundanceData, Samples};
+use libspideog::{
+    bracken::{AbundanceValues, BrackenData},
+    data::abundance::{AbundanceData, Lineage, Samples},
+    kraken::{Fragments, Taxon},
+    taxonomy::Rank,
+};
 use serde::Serialize;
 
+use super::AbundanceValue;
+
 pub trait WriteAbundanceCsv: Sized {
-    fn write_csv<W: std::io::Write>(self, writer: &mut W) -> Result<(), Report> {
+    /// With `lineage`, the [`LineageColumns`] are added after the columns of the values
+    fn write_csv<W: std::io::Write>(self, writer: &mut W, lineage: bool) -> Result<(), Report> {
         let mut csv_writer = csv::WriterBuilder::new()
             .delimiter(b',')
             .quote_style(csv::QuoteStyle::NonNumeric)
             .has_headers(true)
             .from_writer(writer);
 
-        self.write_records(&mut csv_writer)?;
+        self.write_records(&mut csv_writer, lineage)?;
 
         Ok(())
     }
 
-    fn write_records<W: std::io::Write>(self, csv_writer: &mut Writer<W>) -> Result<(), Report>;
+    fn write_records<W: std::io::Write>(
+        self,
+        csv_writer: &mut Writer<W>,
+        lineage: bool,
+    ) -> Result<(), Report>;
+}
+
+/// Canonical ranks of the lineage columns
+const CANONICAL_RANKS: [Rank; 8] = [
+    Rank::Domain(0),
+    Rank::Kingdom(0),
+    Rank::Phylum(0),
+    Rank::Class(0),
+    Rank::Order(0),
+    Rank::Family(0),
+    Rank::Genus(0),
+    Rank::Species(0),
+];
+
+/// Names of the canonical ranks in the lineage of a taxon, and the lineage as a single string, e.g.
+/// `d__Bacteria;p__Proteobacteria;g__Escherichia`
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct LineageColumns {
+    domain: Option<String>,
+    kingdom: Option<String>,
+    phylum: Option<String>,
+    class: Option<String>,
+    order: Option<String>,
+    family: Option<String>,
+    genus: Option<String>,
+    species: Option<String>,
+    lineage: String,
+}
+
+impl LineageColumns {
+    const HEADERS: [&'static str; 9] = [
+        "domain", "kingdom", "phylum", "class", "order", "family", "genus", "species", "lineage",
+    ];
+
+    /// The lineage of the taxon is its own when it is unknown
+    #[must_use]
+    pub fn of(taxon: &Taxon, lineage: Option<&Lineage>) -> Self {
+        let own = [taxon.clone()];
+        let lineage = lineage.map_or(&own[..], |lineage| &lineage[..]);
+
+        let mut names: [Option<String>; 8] = Default::default();
+        for taxon in lineage {
+            if let Some(position) = CANONICAL_RANKS
+                .iter()
+                .position(|rank| *rank == taxon.taxonomy_level)
+            {
+                names[position] = Some(taxon.name.clone());
+            }
+        }
+
+        let lineage = CANONICAL_RANKS
+            .iter()
+            .zip(names.iter())
+            .filter_map(|(rank, name)| {
+                name.as_ref()
+                    .map(|name| format!("{}__{}", rank.letter().to_ascii_lowercase(), name))
+            })
+            .collect::<Vec<String>>()
+            .join(";");
+
+        let [domain, kingdom, phylum, class, order, family, genus, species] = names;
+
+        Self {
+            domain,
+            kingdom,
+            phylum,
+            class,
+            order,
+            family,
+            genus,
+            species,
+            lineage,
+        }
+    }
+
+    fn into_record(self) -> impl Iterator<Item = String> {
+        vec![
+            self.domain,
+            self.kingdom,
+            self.phylum,
+            self.class,
+            self.order,
+            self.family,
+            self.genus,
+            self.species,
+        ]
+        .into_iter()
+        .map(Option::unwrap_or_default)
+        .chain(std::iter::once(self.lineage))
+    }
+}
+
+/// Serialize a row, followed by the lineage columns when they are written
+fn serialize_row<W: std::io::Write, R: Serialize>(
+    csv_writer: &mut Writer<W>,
+    row: R,
+    lineage: Option<LineageColumns>,
+) -> Result<(), csv::Error> {
+    match lineage {
+        Some(lineage) => csv_writer.serialize((row, lineage)),
+        None => csv_writer.serialize(row),
+    }
 }
 
 #[derive(Serialize)]
@@ -31,6 +145,16 @@ struct RowAbundanceData {
     clade_percentage: f64,
     clade_count_reads: u64,
     taxon_count_reads: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    minimizers: Option<Option<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distinct_minimizers: Option<Option<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unique_kmers: Option<Option<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplication: Option<Option<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    coverage: Option<Option<f64>>,
 }
 
 #[derive(Serialize)]
@@ -45,21 +169,117 @@ struct RowSampleAbundanceData {
     clade_percentage: f64,
     clade_count_reads: u64,
     taxon_count_reads: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    minimizers: Option<Option<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distinct_minimizers: Option<Option<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unique_kmers: Option<Option<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplication: Option<Option<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    coverage: Option<Option<f64>>,
+}
+
+#[derive(Serialize)]
+struct RowBrackenData {
+    #[serde(rename = "taxon")]
+    name: String,
+    #[serde(rename = "taxid")]
+    taxonomy_id: u64,
+    #[serde(rename = "rank")]
+    taxonomy_level: String,
+    kraken_assigned_reads: u64,
+    added_reads: u64,
+    new_est_reads: u64,
+    fraction_total_reads: f64,
+}
+
+#[derive(Serialize)]
+struct RowSampleBrackenData {
+    sample: String,
+    #[serde(rename = "taxon")]
+    name: String,
+    #[serde(rename = "taxid")]
+    taxonomy_id: u64,
+    #[serde(rename = "rank")]
+    taxonomy_level: String,
+    kraken_assigned_reads: u64,
+    added_reads: u64,
+    new_est_reads: u64,
+    fraction_total_reads: f64,
+}
+
+/// Columns written only when at least one record of the output has the values
+#[derive(Debug, Default, Clone, Copy)]
+struct OptionalColumns {
+    minimizers: bool,
+    kmers: bool,
+}
+
+impl OptionalColumns {
+    fn of<'a, I: IntoIterator<Item = &'a Fragments>>(fragments: I) -> Self {
+        fragments
+            .into_iter()
+            .fold(Self::default(), |columns, fragments| Self {
+                minimizers: columns.minimizers || fragments.minimizers.is_some(),
+                kmers: columns.kmers || fragments.kmers.is_some(),
+            })
+    }
+
+    /// Values of the optional columns, `None` when the column is not written
+    fn values(self, fragments: &Fragments) -> OptionalValues {
+        let minimizers = fragments.minimizers;
+        let kmers = fragments.kmers;
+
+        OptionalValues {
+            minimizers: Some(minimizers.map(|m| m.minimizers)).filter(|_| self.minimizers),
+            distinct_minimizers: Some(minimizers.map(|m| m.distinct_minimizers))
+                .filter(|_| self.minimizers),
+            unique_kmers: Some(kmers.map(|k| k.unique_kmers)).filter(|_| self.kmers),
+            duplication: Some(kmers.map(|k| k.duplication)).filter(|_| self.kmers),
+            coverage: Some(kmers.and_then(|k| k.coverage)).filter(|_| self.kmers),
+        }
+    }
+}
+
+struct OptionalValues {
+    minimizers: Option<Option<u64>>,
+    distinct_minimizers: Option<Option<u64>>,
+    unique_kmers: Option<Option<u64>>,
+    duplication: Option<Option<f64>>,
+    coverage: Option<Option<f64>>,
 }
 
 impl WriteAbundanceCsv for AbundanceData {
-    fn write_records<W: std::io::Write>(self, csv_writer: &mut Writer<W>) -> Result<(), Report> {
-        for (taxon, abundance_data) in self {
-            csv_writer
-                .serialize(RowAbundanceData {
+    fn write_records<W: std::io::Write>(
+        self,
+        csv_writer: &mut Writer<W>,
+        lineage: bool,
+    ) -> Result<(), Report> {
+        let columns = OptionalColumns::of(self.values());
+
+        for (taxon, abundance_data) in &self {
+            let optional = columns.values(abundance_data);
+
+            serialize_row(
+                csv_writer,
+                RowAbundanceData {
                     name: taxon.name.clone(),
                     taxonomy_id: taxon.taxonomy_id,
                     taxonomy_level: format!("{}", taxon.taxonomy_level),
                     clade_percentage: abundance_data.clade_percentage,
                     clade_count_reads: abundance_data.clade_count_reads,
                     taxon_count_reads: abundance_data.taxon_count_reads,
-                })
-                .wrap_err_with(|| format!("failed to write record for `{}`", taxon.name))?;
+                    minimizers: optional.minimizers,
+                    distinct_minimizers: optional.distinct_minimizers,
+                    unique_kmers: optional.unique_kmers,
+                    duplication: optional.duplication,
+                    coverage: optional.coverage,
+                },
+                lineage.then(|| LineageColumns::of(taxon, self.lineage(taxon))),
+            )
+            .wrap_err_with(|| format!("failed to write record for `{}`", taxon.name))?;
         }
 
         Ok(())
@@ -67,11 +287,21 @@ impl WriteAbundanceCsv for AbundanceData {
 }
 
 impl WriteAbundanceCsv for Samples {
-    fn write_records<W: std::io::Write>(self, csv_writer: &mut Writer<W>) -> Result<(), Report> {
-        for sample in self.data {
+    fn write_records<W: std::io::Write>(
+        self,
+        csv_writer: &mut Writer<W>,
+        lineage: bool,
+    ) -> Result<(), Report> {
+        let columns =
+            OptionalColumns::of(self.data.iter().flat_map(|sample| sample.dataset.values()));
+
+        for sample in &self.data {
             for (taxon, abundance_data) in &sample.dataset {
-                csv_writer
-                    .serialize(RowSampleAbundanceData {
+                let optional = columns.values(abundance_data);
+
+                serialize_row(
+                    csv_writer,
+                    RowSampleAbundanceData {
                         sample: sample.name.clone(),
                         name: taxon.name.clone(),
                         taxonomy_id: taxon.taxonomy_id,
@@ -79,16 +309,271 @@ impl WriteAbundanceCsv for Samples {
                         clade_percentage: abundance_data.clade_percentage,
                         clade_count_reads: abundance_data.clade_count_reads,
                         taxon_count_reads: abundance_data.taxon_count_reads,
-                    })
-                    .wrap_err_with(|| {
-                        format!(
-                            "failed to write record for sample `{}` `{}`",
-                            sample.name, taxon.name
-                        )
-                    })?;
+                        minimizers: optional.minimizers,
+                        distinct_minimizers: optional.distinct_minimizers,
+                        unique_kmers: optional.unique_kmers,
+                        duplication: optional.duplication,
+                        coverage: optional.coverage,
+                    },
+                    lineage.then(|| LineageColumns::of(taxon, self.lineage(taxon))),
+                )
+                .wrap_err_with(|| {
+                    format!(
+                        "failed to write record for sample `{}` `{}`",
+                        sample.name, taxon.name
+                    )
+                })?;
             }
         }
 
         Ok(())
     }
 }
+
+impl WriteAbundanceCsv for BrackenData {
+    fn write_records<W: std::io::Write>(
+        self,
+        csv_writer: &mut Writer<W>,
+        lineage: bool,
+    ) -> Result<(), Report> {
+        for (taxon, abundance_values) in &self {
+            serialize_row(
+                csv_writer,
+                RowBrackenData {
+                    name: taxon.name.clone(),
+                    taxonomy_id: taxon.taxonomy_id,
+                    taxonomy_level: format!("{}", taxon.taxonomy_level),
+                    kraken_assigned_reads: abundance_values.kraken_assigned_reads,
+                    added_reads: abundance_values.added_reads,
+                    new_est_reads: abundance_values.new_est_reads,
+                    fraction_total_reads: abundance_values.fraction_total_reads,
+                },
+                lineage.then(|| LineageColumns::of(taxon, self.lineage(taxon))),
+            )
+            .wrap_err_with(|| format!("failed to write record for `{}`", taxon.name))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl WriteAbundanceCsv for Samples<AbundanceValues> {
+    fn write_records<W: std::io::Write>(
+        self,
+        csv_writer: &mut Writer<W>,
+        lineage: bool,
+    ) -> Result<(), Report> {
+        for sample in &self.data {
+            for (taxon, abundance_values) in &sample.dataset {
+                serialize_row(
+                    csv_writer,
+                    RowSampleBrackenData {
+                        sample: sample.name.clone(),
+                        name: taxon.name.clone(),
+                        taxonomy_id: taxon.taxonomy_id,
+                        taxonomy_level: format!("{}", taxon.taxonomy_level),
+                        kraken_assigned_reads: abundance_values.kraken_assigned_reads,
+                        added_reads: abundance_values.added_reads,
+                        new_est_reads: abundance_values.new_est_reads,
+                        fraction_total_reads: abundance_values.fraction_total_reads,
+                    },
+                    lineage.then(|| LineageColumns::of(taxon, self.lineage(taxon))),
+                )
+                .wrap_err_with(|| {
+                    format!(
+                        "failed to write record for sample `{}` `{}`",
+                        sample.name, taxon.name
+                    )
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Value of a taxon in the wide layout
+pub trait WideValue {
+    fn wide_value(&self, value: AbundanceValue) -> f64;
+}
+
+impl WideValue for Fragments {
+    #[allow(clippy::cast_precision_loss)]
+    fn wide_value(&self, value: AbundanceValue) -> f64 {
+        match value {
+            AbundanceValue::CladeReads => self.clade_count_reads as f64,
+            AbundanceValue::TaxonReads => self.taxon_count_reads as f64,
+            AbundanceValue::Percentage => self.clade_percentage,
+        }
+    }
+}
+
+/// Bracken estimates the reads of whole clades, both read values are the new estimated reads
+impl WideValue for AbundanceValues {
+    #[allow(clippy::cast_precision_loss)]
+    fn wide_value(&self, value: AbundanceValue) -> f64 {
+        match value {
+            AbundanceValue::CladeReads | AbundanceValue::TaxonReads => self.new_est_reads as f64,
+            AbundanceValue::Percentage => self.fraction_total_reads * 100.0,
+        }
+    }
+}
+
+/// Write samples as a taxa by samples matrix: the taxon columns first, then one column per sample,
+/// taxa missing from a sample have a zero value
+pub trait WriteWideCsv {
+    fn write_wide_csv<W: std::io::Write>(
+        self,
+        writer: &mut W,
+        value: AbundanceValue,
+        lineage: bool,
+    ) -> Result<(), Report>;
+}
+
+impl<V: WideValue> WriteWideCsv for Samples<V> {
+    fn write_wide_csv<W: std::io::Write>(
+        self,
+        writer: &mut W,
+        value: AbundanceValue,
+        lineage: bool,
+    ) -> Result<(), Report> {
+        let mut csv_writer = csv::WriterBuilder::new()
+            .delimiter(b',')
+            .quote_style(csv::QuoteStyle::NonNumeric)
+            .from_writer(writer);
+
+        let lineage_headers: &[&str] = if lineage {
+            &LineageColumns::HEADERS
+        } else {
+            &[]
+        };
+        let header = ["taxid", "taxon", "rank"]
+            .iter()
+            .chain(lineage_headers)
+            .map(ToString::to_string)
+            .chain(self.data.iter().map(|sample| sample.name.clone()));
+        csv_writer
+            .write_record(header)
+            .wrap_err("failed to write header")?;
+
+        for taxon in &self.unique_taxons {
+            let values = self.data.iter().map(|sample| {
+                sample
+                    .dataset
+                    .get(taxon)
+                    .map_or(0.0, |values| values.wide_value(value))
+                    .to_string()
+            });
+            let lineage_values = lineage
+                .then(|| LineageColumns::of(taxon, self.lineage(taxon)).into_record())
+                .into_iter()
+                .flatten();
+
+            csv_writer
+                .write_record(
+                    vec![
+                        taxon.taxonomy_id.to_string(),
+                        taxon.name.clone(),
+                        taxon.taxonomy_level.to_string(),
+                    ]
+                    .into_iter()
+                    .chain(lineage_values)
+                    .chain(values),
+                )
+                .wrap_err_with(|| format!("failed to write record for `{}`", taxon.name))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libspideog::{kraken::Taxon, taxonomy::Rank};
+    use std::iter::FromIterator;
+    use test_case::test_case;
+
+    fn samples() -> Samples {
+        let fragments = |clade_count_reads, taxon_count_reads, clade_percentage| Fragments {
+            clade_percentage,
+            clade_count_reads,
+            taxon_count_reads,
+            ..Fragments::default()
+        };
+
+        let first: AbundanceData = vec![(
+            Taxon::new(561, "Escherichia", Rank::Genus(0)),
+            fragments(12, 10, 1.5),
+        )]
+        .into_iter()
+        .collect();
+        let second: AbundanceData = vec![(
+            Taxon::new(1386, "Bacillus, sp.", Rank::Genus(0)),
+            fragments(4, 4, 0.25),
+        )]
+        .into_iter()
+        .collect();
+
+        Samples::from_iter(vec![
+            ("first".to_string(), first),
+            ("second".to_string(), second),
+        ])
+    }
+
+    #[test_case(AbundanceValue::CladeReads => "\"taxid\",\"taxon\",\"rank\",\"first\",\"second\"\n561,\"Escherichia\",\"Genus_0\",12,0\n1386,\"Bacillus, sp.\",\"Genus_0\",0,4\n")]
+    #[test_case(AbundanceValue::TaxonReads => "\"taxid\",\"taxon\",\"rank\",\"first\",\"second\"\n561,\"Escherichia\",\"Genus_0\",10,0\n1386,\"Bacillus, sp.\",\"Genus_0\",0,4\n")]
+    #[test_case(AbundanceValue::Percentage => "\"taxid\",\"taxon\",\"rank\",\"first\",\"second\"\n561,\"Escherichia\",\"Genus_0\",1.5,0\n1386,\"Bacillus, sp.\",\"Genus_0\",0,0.25\n")]
+    fn test_write_wide_csv(value: AbundanceValue) -> String {
+        let mut output = Vec::new();
+        samples().write_wide_csv(&mut output, value, false).unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_lineage_columns() {
+        let lineage = vec![
+            Taxon::new(1, "root", Rank::Root(0)),
+            Taxon::new(2, "Bacteria", Rank::Domain(0)),
+            Taxon::new(1224, "Proteobacteria", Rank::Phylum(0)),
+            Taxon::new(1236, "Gammaproteobacteria", Rank::Class(0)),
+            Taxon::new(91347, "Enterobacterales", Rank::Order(0)),
+            Taxon::new(1903409, "Enterobacterales incertae sedis", Rank::Order(1)),
+            Taxon::new(562, "Escherichia coli", Rank::Species(0)),
+        ];
+
+        let columns = LineageColumns::of(&lineage[6], Some(&lineage));
+        pretty_assertions::assert_eq!(
+            columns.into_record().collect::<Vec<String>>(),
+            vec![
+                "Bacteria",
+                "",
+                "Proteobacteria",
+                "Gammaproteobacteria",
+                "Enterobacterales",
+                "",
+                "",
+                "Escherichia coli",
+                "d__Bacteria;p__Proteobacteria;c__Gammaproteobacteria;o__Enterobacterales;s__Escherichia coli"
+            ]
+        );
+
+        pretty_assertions::assert_eq!(
+            LineageColumns::of(&lineage[2], None).lineage,
+            "p__Proteobacteria"
+        );
+    }
+
+    #[test]
+    fn test_write_csv_lineage() {
+        let mut output = Vec::new();
+        samples().write_csv(&mut output, true).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.starts_with("\"sample\",\"taxon\",\"taxid\",\"rank\",\"clade_percentage\",\"clade_count_reads\",\"taxon_count_reads\",\"domain\",\"kingdom\",\"phylum\",\"class\",\"order\",\"family\",\"genus\",\"species\",\"lineage\"\n"));
+        assert!(output.contains(
+            "\"first\",\"Escherichia\",561,\"Genus_0\",1.5,12,10,\"\",\"\",\"\",\"\",\"\",\"\",\"Escherichia\",\"\",\"g__Escherichia\"\n"
+        ));
+    }
+}
@@ -0,0 +1,357 @@
+use color_eyre::Report;
+use eyre::Context;
+use libspideog::{
+    bracken::AbundanceValues,
+    data::abundance::Samples,
+    kraken::{Fragments, Taxon},
+    taxonomy::Rank,
+};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Write samples as a BIOM 1.0 (JSON) table, see <http://biom-format.org/documentation/format_versions/biom-1.0.html>
+///
+/// The matrix holds the reads assigned directly to each taxon (`taxon_count_reads`, or
+/// `new_est_reads` for Bracken tables), the unclassified reads are left out.
+pub trait WriteBiom: Sized {
+    fn write_biom<W: std::io::Write>(self, writer: &mut W) -> Result<(), Report>;
+}
+
+impl WriteBiom for Samples {
+    fn write_biom<W: std::io::Write>(self, writer: &mut W) -> Result<(), Report> {
+        let table = BiomTable::new(&self, |fragments: &Fragments| fragments.taxon_count_reads);
+
+        serde_json::to_writer(writer, &table).wrap_err("failed to write BIOM table")
+    }
+}
+
+impl WriteBiom for Samples<AbundanceValues> {
+    fn write_biom<W: std::io::Write>(self, writer: &mut W) -> Result<(), Report> {
+        let table = BiomTable::new(&self, |values: &AbundanceValues| values.new_est_reads);
+
+        serde_json::to_writer(writer, &table).wrap_err("failed to write BIOM table")
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct BiomTable {
+    id: Option<String>,
+    format: &'static str,
+    format_url: &'static str,
+    #[serde(rename = "type")]
+    table_type: &'static str,
+    generated_by: String,
+    date: String,
+    rows: Vec<BiomRow>,
+    columns: Vec<BiomColumn>,
+    matrix_type: &'static str,
+    matrix_element_type: &'static str,
+    shape: [usize; 2],
+    data: Vec<[u64; 3]>,
+}
+
+#[derive(Serialize, Debug)]
+struct BiomRow {
+    id: String,
+    metadata: RowMetadata,
+}
+
+#[derive(Serialize, Debug)]
+struct RowMetadata {
+    taxonomy: Vec<String>,
+    taxid: u64,
+    name: String,
+    rank: String,
+}
+
+#[derive(Serialize, Debug)]
+struct BiomColumn {
+    id: String,
+    metadata: Option<()>,
+}
+
+impl BiomTable {
+    /// Observations (rows) are the classified taxa and samples are the columns, only the non-zero
+    /// counts are stored
+    fn new<V, F: Fn(&V) -> u64>(samples: &Samples<V>, count: F) -> Self {
+        let observations: Vec<&Taxon> = samples
+            .unique_taxons
+            .iter()
+            .filter(|taxon| !matches!(taxon.taxonomy_level, Rank::Unclassified(_)))
+            .collect();
+
+        let mut ids = ObservationIds::default();
+        let rows: Vec<BiomRow> = observations
+            .iter()
+            .map(|taxon| BiomRow {
+                id: ids.next(taxon.taxonomy_id),
+                metadata: RowMetadata {
+                    taxonomy: taxonomy_strings(taxon, samples.lineage(taxon)),
+                    taxid: taxon.taxonomy_id,
+                    name: taxon.name.clone(),
+                    rank: taxon.taxonomy_level.to_string(),
+                },
+            })
+            .collect();
+
+        let columns: Vec<BiomColumn> = samples
+            .data
+            .iter()
+            .map(|sample| BiomColumn {
+                id: sample.name.clone(),
+                metadata: None,
+            })
+            .collect();
+
+        let count = &count;
+        let data = observations
+            .iter()
+            .enumerate()
+            .flat_map(|(row, taxon)| {
+                samples
+                    .data
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(column, sample)| {
+                        sample
+                            .dataset
+                            .get(taxon)
+                            .map(count)
+                            .filter(|value| *value > 0)
+                            .map(|value| [row as u64, column as u64, value])
+                    })
+            })
+            .collect();
+
+        Self {
+            id: None,
+            format: "Biological Observation Matrix 1.0.0",
+            format_url: "http://biom-format.org",
+            table_type: "OTU table",
+            generated_by: format!("spideog {}", env!("CARGO_PKG_VERSION")),
+            date: now(),
+            shape: [rows.len(), columns.len()],
+            rows,
+            columns,
+            matrix_type: "sparse",
+            matrix_element_type: "int",
+            data,
+        }
+    }
+}
+
+/// Identifiers of the observations, the taxids made unique: the same taxid can be found with
+/// different names or ranks in the reports, and MetaPhlAn 2 profiles have no taxids (all `0`)
+#[derive(Default)]
+struct ObservationIds {
+    seen: HashMap<u64, usize>,
+}
+
+impl ObservationIds {
+    /// The taxid for its first observation, then suffixed with `-1`, `-2`, ...
+    fn next(&mut self, taxonomy_id: u64) -> String {
+        let seen = self.seen.entry(taxonomy_id).or_insert(0);
+        let id = if *seen == 0 {
+            taxonomy_id.to_string()
+        } else {
+            format!("{}-{}", taxonomy_id, seen)
+        };
+        *seen += 1;
+
+        id
+    }
+}
+
+/// Taxonomy of an observation at the main ranks, e.g. `["k__Bacteria", "p__Proteobacteria", ...]`
+///
+/// The domain is written as the kingdom (`k__`), the kingdom is only written for lineages without domain.
+fn taxonomy_strings(taxon: &Taxon, lineage: Option<&Vec<Taxon>>) -> Vec<String> {
+    let lineage = lineage.map_or_else(|| std::slice::from_ref(taxon), Vec::as_slice);
+    let has_domain = lineage
+        .iter()
+        .any(|ancestor| ancestor.taxonomy_level == Rank::Domain(0));
+
+    lineage
+        .iter()
+        .filter_map(|ancestor| {
+            let prefix = match ancestor.taxonomy_level {
+                Rank::Domain(0) => 'k',
+                Rank::Kingdom(0) if !has_domain => 'k',
+                Rank::Phylum(0)
+                | Rank::Class(0)
+                | Rank::Order(0)
+                | Rank::Family(0)
+                | Rank::Genus(0)
+                | Rank::Species(0) => ancestor.taxonomy_level.letter().to_ascii_lowercase(),
+                _ => return None,
+            };
+
+            Some(format!("{}__{}", prefix, ancestor.name))
+        })
+        .collect()
+}
+
+fn now() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+
+    iso_8601(seconds)
+}
+
+/// Format a UNIX timestamp as an ISO 8601 date time (UTC)
+fn iso_8601(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let time = seconds % 86_400;
+
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libspideog::data::abundance::AbundanceData;
+    use std::iter::FromIterator;
+    use test_case::test_case;
+
+    fn data(values: Vec<(Taxon, u64, Vec<Taxon>)>) -> AbundanceData {
+        let mut data = AbundanceData::new();
+        for (taxon, taxon_count_reads, lineage) in values {
+            data.lineages.insert(taxon.clone(), lineage);
+            data.insert(
+                taxon,
+                Fragments {
+                    taxon_count_reads,
+                    ..Fragments::default()
+                },
+            );
+        }
+        data
+    }
+
+    #[test_case(0 => "1970-01-01T00:00:00")]
+    #[test_case(951_782_400 => "2000-02-29T00:00:00")]
+    #[test_case(1_792_321_845 => "2026-10-18T11:10:45")]
+    fn test_iso_8601(seconds: u64) -> String {
+        iso_8601(seconds)
+    }
+
+    #[test]
+    fn test_biom_table() {
+        let root = Taxon::new(1, "root", Rank::Root(0));
+        let bacteria = Taxon::new(2, "Bacteria", Rank::Domain(0));
+        let proteobacteria = Taxon::new(1224, "Proteobacteria", Rank::Phylum(0));
+        let gammaproteobacteria = Taxon::new(1236, "Gammaproteobacteria", Rank::Class(0));
+        let lineage = vec![
+            root.clone(),
+            bacteria.clone(),
+            proteobacteria.clone(),
+            gammaproteobacteria.clone(),
+        ];
+
+        let unclassified = Taxon::new(0, "unclassified", Rank::Unclassified(0));
+
+        let first = data(vec![
+            (unclassified.clone(), 7, vec![unclassified]),
+            (bacteria.clone(), 10, lineage[..2].to_vec()),
+            (gammaproteobacteria.clone(), 0, lineage.clone()),
+        ]);
+        let second = data(vec![(bacteria, 3, lineage[..2].to_vec())]);
+
+        let samples = Samples::from_iter(vec![
+            ("first".to_string(), first),
+            ("second".to_string(), second),
+        ]);
+        let table = BiomTable::new(&samples, |fragments: &Fragments| {
+            fragments.taxon_count_reads
+        });
+
+        pretty_assertions::assert_eq!(table.shape, [2, 2]);
+        pretty_assertions::assert_eq!(table.columns[1].id, "second");
+
+        let row = table
+            .rows
+            .iter()
+            .position(|row| row.metadata.taxid == 1236)
+            .unwrap();
+        pretty_assertions::assert_eq!(
+            table.rows[row].metadata.taxonomy,
+            vec!["k__Bacteria", "p__Proteobacteria", "c__Gammaproteobacteria"]
+        );
+
+        let other = 1 - row as u64;
+        pretty_assertions::assert_eq!(table.data, vec![[other, 0, 10], [other, 1, 3]]);
+    }
+
+    #[test]
+    fn test_biom_table_unique_ids() {
+        // same taxid with another rank in the second report, and taxids missing from MetaPhlAn 2
+        let first = data(vec![
+            (
+                Taxon::new(562, "Escherichia coli", Rank::Species(0)),
+                5,
+                vec![],
+            ),
+            (Taxon::new(0, "k__Bacteria", Rank::Domain(0)), 2, vec![]),
+            (Taxon::new(0, "p__Firmicutes", Rank::Phylum(0)), 1, vec![]),
+        ]);
+        let second = data(vec![(
+            Taxon::new(562, "Escherichia coli", Rank::Species(1)),
+            3,
+            vec![],
+        )]);
+
+        let samples = Samples::from_iter(vec![
+            ("first".to_string(), first),
+            ("second".to_string(), second),
+        ]);
+        let table = BiomTable::new(&samples, |fragments: &Fragments| {
+            fragments.taxon_count_reads
+        });
+
+        let mut ids: Vec<&str> = table.rows.iter().map(|row| row.id.as_str()).collect();
+        ids.sort_unstable();
+        pretty_assertions::assert_eq!(ids, vec!["0", "0-1", "562", "562-1"]);
+    }
+
+    #[test]
+    fn test_taxonomy_strings_kingdom() {
+        let metazoa = Taxon::new(33208, "Metazoa", Rank::Kingdom(0));
+        let lineage = vec![
+            Taxon::new(1, "root", Rank::Root(0)),
+            Taxon::new(2759, "Eukaryota", Rank::Domain(0)),
+            metazoa.clone(),
+        ];
+
+        pretty_assertions::assert_eq!(
+            taxonomy_strings(&metazoa, Some(&lineage)),
+            vec!["k__Eukaryota"]
+        );
+        pretty_assertions::assert_eq!(
+            taxonomy_strings(&metazoa, Some(&lineage[2..].to_vec())),
+            vec!["k__Metazoa"]
+        );
+    }
+}
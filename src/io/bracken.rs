@@ -0,0 +1,26 @@
+use std::io::Read;
+
+use csv::Reader;
+use libspideog::{
+    bracken::{BrackenData, BrackenRecord},
+    errors::SpideogError,
+};
+use tracing::instrument;
+
+pub trait ParseBrackenReport: Sized {
+    fn parse<R: Read>(reader: &mut Reader<R>) -> Result<Self, SpideogError>;
+}
+
+impl ParseBrackenReport for BrackenData {
+    #[instrument(skip(reader))]
+    fn parse<R: Read>(reader: &mut Reader<R>) -> Result<Self, SpideogError> {
+        let mut data = Self::new();
+
+        for result in reader.deserialize() {
+            let record: BrackenRecord = result.map_err(SpideogError::BrackenParser)?;
+            data.insert(record.taxon, record.abundance_values);
+        }
+
+        Ok(data)
+    }
+}
@@ -0,0 +1,122 @@
+use super::InputReportFormat;
+
+/// Format and header line of a report, guessed from its first lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detected {
+    pub format: InputReportFormat,
+    pub headers: bool,
+}
+
+const BRACKEN_HEADER: [&str; 3] = ["name", "taxonomy_id", "taxonomy_lvl"];
+
+/// Comment lines written by MetaPhlAn before the profile
+fn is_metaphlan_comment(line: &str) -> bool {
+    line.starts_with("#mpa_") || line.starts_with("#clade_name") || line.starts_with("#SampleID")
+}
+
+/// MetaPhlAn clade lineage such as `k__Bacteria|p__Firmicutes`
+fn is_metaphlan_clade(field: &str) -> bool {
+    field == "UNCLASSIFIED"
+        || field == "UNKNOWN"
+        || field
+            .split('|')
+            .all(|clade| clade.len() > 3 && clade.as_bytes()[1..3] == *b"__")
+}
+
+/// Format of a single line without any context
+fn detect_line(fields: &[&str]) -> Option<InputReportFormat> {
+    let is_number = |field: &str| field.trim().parse::<f64>().is_ok();
+
+    match fields {
+        ["%", "reads", "taxReads", ..] => Some(InputReportFormat::KrakenUniq),
+        [name, taxid, rank, ..] if [*name, *taxid, *rank] == BRACKEN_HEADER => {
+            Some(InputReportFormat::Bracken)
+        }
+        ["C", ..] | ["U", ..] if fields.len() == 5 => Some(InputReportFormat::KrakenReads),
+        [percentage, ..] if (fields.len() == 6 || fields.len() == 8) && is_number(percentage) => {
+            Some(InputReportFormat::Kraken)
+        }
+        [clade, ..] if is_metaphlan_clade(clade) => Some(InputReportFormat::Metaphlan),
+        _ => None,
+    }
+}
+
+/// Guess the format of a report from its beginning
+///
+/// Kraken2 reports with or without minimizer data are both detected as `Kraken`, the layout is
+/// picked later from the number of columns. An unknown first line followed by a Kraken line is
+/// taken as a header line.
+pub fn detect_format(start: &str) -> Option<Detected> {
+    let mut metaphlan_comments = false;
+    let mut data_lines = Vec::with_capacity(2);
+
+    for line in start.lines().filter(|line| !line.trim().is_empty()) {
+        if line.starts_with('#') {
+            metaphlan_comments |= is_metaphlan_comment(line);
+        } else {
+            data_lines.push(line);
+        }
+
+        if data_lines.len() == 2 {
+            break;
+        }
+    }
+
+    // other tools write comment lines too, the comments are only trusted with MetaPhlAn data lines
+    let metaphlan_data = data_lines
+        .first()
+        .is_none_or(|line| line.split('\t').next().is_some_and(is_metaphlan_clade));
+
+    if metaphlan_comments && metaphlan_data {
+        return Some(Detected {
+            format: InputReportFormat::Metaphlan,
+            headers: false,
+        });
+    }
+
+    let fields: Vec<Vec<&str>> = data_lines
+        .iter()
+        .map(|line| line.split('\t').collect())
+        .collect();
+
+    match fields.as_slice() {
+        [first, ..] if detect_line(first).is_some() => detect_line(first).map(|format| Detected {
+            format,
+            headers: format.has_headers(),
+        }),
+        [_, second] => match detect_line(second) {
+            Some(format @ InputReportFormat::Kraken)
+            | Some(format @ InputReportFormat::KrakenReads) => Some(Detected {
+                format,
+                headers: true,
+            }),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("100.00\t12\t0\tR\t1\troot\n 90.00\t10\t10\tD\t2\t  Bacteria\n", InputReportFormat::Kraken, false ; "kraken")]
+    #[test_case("100.00\t12\t0\t100\t80\tR\t1\troot\n", InputReportFormat::Kraken, false ; "minimizers")]
+    #[test_case("percentage\tclade\ttaxon\trank\ttaxid\tname\n100.00\t12\t0\tR\t1\troot\n", InputReportFormat::Kraken, true ; "kraken with headers")]
+    #[test_case("# KrakenUniq v0.5.8 DATE:2019-07-30\n# Database: db\n%\treads\ttaxReads\tkmers\tdup\tcov\ttaxID\trank\ttaxName\n", InputReportFormat::KrakenUniq, true ; "krakenuniq")]
+    #[test_case("#KrakenUniq v0.5.8\n#SampleID\tsample\n# Database(s): /db/metaphlan_db\n%\treads\ttaxReads\tkmers\tdup\tcov\ttaxID\trank\ttaxName\n", InputReportFormat::KrakenUniq, true ; "krakenuniq with a metaphlan database")]
+    #[test_case("name\ttaxonomy_id\ttaxonomy_lvl\tkraken_assigned_reads\tadded_reads\tnew_est_reads\tfraction_total_reads\n", InputReportFormat::Bracken, true ; "bracken")]
+    #[test_case("#mpa_v30_CHOCOPhlAn_201901\n#clade_name\tNCBI_tax_id\trelative_abundance\n", InputReportFormat::Metaphlan, false ; "metaphlan")]
+    #[test_case("k__Bacteria\t100.0\nk__Bacteria|p__Firmicutes\t60.0\n", InputReportFormat::Metaphlan, false ; "metaphlan without comments")]
+    #[test_case("C\tread1\t562\t150|148\t562:10 0:5\nU\tread2\t0\t150\t0:30\n", InputReportFormat::KrakenReads, false ; "kraken reads")]
+    fn test_detect_format(start: &str, format: InputReportFormat, headers: bool) {
+        pretty_assertions::assert_eq!(detect_format(start), Some(Detected { format, headers }));
+    }
+
+    #[test_case("" ; "empty")]
+    #[test_case("some\tunknown\ttable\nwith\ttwo\tlines\n" ; "unknown")]
+    fn test_detect_format_unknown(start: &str) {
+        pretty_assertions::assert_eq!(detect_format(start), None);
+    }
+}
@@ -0,0 +1,112 @@
+use color_eyre::{Help, Report};
+use libspideog::data::abundance::SampleName;
+use std::{collections::HashMap, io};
+
+/// Tab separated square matrix, the first row and the first column have the sample names
+pub fn write_tsv<W: io::Write>(
+    writer: &mut W,
+    samples: &[SampleName],
+    matrix: &[Vec<f64>],
+) -> Result<(), Report> {
+    for sample in samples {
+        write!(writer, "\t{}", sample)?;
+    }
+    writeln!(writer)?;
+
+    for (sample, row) in samples.iter().zip(matrix) {
+        write!(writer, "{}", sample)?;
+        for value in row {
+            write!(writer, "\t{}", value)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Square PHYLIP distance matrix: the number of samples on the first line then one row per sample.
+/// The names are truncated or padded to ten characters and their whitespaces are replaced by
+/// underscores, the samples whose truncated names are the same are rejected.
+pub fn write_phylip<W: io::Write>(
+    writer: &mut W,
+    samples: &[SampleName],
+    matrix: &[Vec<f64>],
+) -> Result<(), Report> {
+    let mut names: HashMap<String, &SampleName> = HashMap::new();
+    for sample in samples {
+        let name = phylip_name(sample);
+        if let Some(other) = names.insert(name.clone(), sample) {
+            return Err(eyre!(
+                "`{}` and `{}` have the same PHYLIP name `{}`",
+                other,
+                sample,
+                name
+            ))
+            .suggestion("PHYLIP names are truncated to ten characters, use `--format tsv` or shorter report names");
+        }
+    }
+
+    writeln!(writer, "{}", samples.len())?;
+
+    for (sample, row) in samples.iter().zip(matrix) {
+        write!(writer, "{:<10}", phylip_name(sample))?;
+        for value in row {
+            write!(writer, " {:.6}", value)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+fn phylip_name(sample: &str) -> String {
+    sample
+        .chars()
+        .map(|c| if c.is_whitespace() { '_' } else { c })
+        .take(10)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix() -> (Vec<SampleName>, Vec<Vec<f64>>) {
+        (
+            vec!["first".to_string(), "second sample".to_string()],
+            vec![vec![0.0, 0.25], vec![0.25, 0.0]],
+        )
+    }
+
+    #[test]
+    fn test_write_tsv() {
+        let (samples, matrix) = matrix();
+        let mut output = Vec::new();
+        write_tsv(&mut output, &samples, &matrix).unwrap();
+
+        pretty_assertions::assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\tfirst\tsecond sample\nfirst\t0\t0.25\nsecond sample\t0.25\t0\n"
+        );
+    }
+
+    #[test]
+    fn test_write_phylip() {
+        let (samples, matrix) = matrix();
+        let mut output = Vec::new();
+        write_phylip(&mut output, &samples, &matrix).unwrap();
+
+        pretty_assertions::assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "2\nfirst      0.000000 0.250000\nsecond_sam 0.250000 0.000000\n"
+        );
+    }
+
+    #[test]
+    fn test_write_phylip_same_names() {
+        let samples = vec!["sample_001_R1".to_string(), "sample_001_R2".to_string()];
+        let matrix = vec![vec![0.0, 0.25], vec![0.25, 0.0]];
+
+        assert!(write_phylip(&mut Vec::new(), &samples, &matrix).is_err());
+    }
+}
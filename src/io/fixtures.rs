@@ -0,0 +1,39 @@
+use daggy::NodeIndex;
+use libspideog::{
+    data::tree::{IndentedTaxon, Reads, Tree},
+    kraken::Taxon,
+    taxonomy::Rank,
+};
+
+pub(crate) fn node(
+    taxonomy_id: u64,
+    name: &str,
+    taxonomy_level: Rank,
+    indent: usize,
+    reads: Option<(u64, u64)>,
+) -> IndentedTaxon {
+    IndentedTaxon {
+        indent,
+        taxon: Taxon::new(taxonomy_id, name, taxonomy_level),
+        reads: reads.map(|(clade, taxon)| Reads { clade, taxon }),
+    }
+}
+
+/// Root, Bacteria without reads and Escherichia coli below it, and Archaea
+pub(crate) fn tree() -> Tree {
+    let mut tree = Tree::new();
+    tree.with_origin(node(1, "root", Rank::Root(0), 0, Some((10, 4))));
+    tree.child(
+        NodeIndex::new(0),
+        node(2, "Bacteria", Rank::Domain(0), 1, None),
+    );
+    tree.child(
+        NodeIndex::new(1),
+        node(562, "Escherichia coli", Rank::Species(0), 2, Some((10, 4))),
+    );
+    tree.child(
+        NodeIndex::new(0),
+        node(2157, "Archaea", Rank::Domain(0), 1, Some((10, 4))),
+    );
+    tree
+}
@@ -0,0 +1,142 @@
+use color_eyre::Report;
+use eyre::Context;
+use libspideog::data::{
+    abundance::Samples,
+    tree::{IndentedTaxon, Tree},
+};
+use serde::Serialize;
+
+/// Write a value as a single JSON document
+pub fn write_json<W, T>(writer: &mut W, value: &T) -> Result<(), Report>
+where
+    W: std::io::Write,
+    T: Serialize,
+{
+    serde_json::to_writer(&mut *writer, value).wrap_err("failed to serialize to JSON")?;
+    writeln!(writer)?;
+
+    Ok(())
+}
+
+/// Write one JSON object per row of the samples
+pub fn write_ndjson_samples<W, V>(writer: &mut W, samples: &Samples<V>) -> Result<(), Report>
+where
+    W: std::io::Write,
+    V: Serialize,
+{
+    for row in samples.rows() {
+        write_json(writer, &row)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct FlatNode<'a> {
+    #[serde(flatten)]
+    node: &'a IndentedTaxon,
+    parent: Option<u64>,
+}
+
+/// Write one JSON object per node of the tree, parents first, with the taxid of their parent
+pub fn write_ndjson_tree<W: std::io::Write>(writer: &mut W, tree: &Tree) -> Result<(), Report> {
+    let mut stack = tree
+        .origin
+        .map(|origin| (origin, None))
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    while let Some((index, parent)) = stack.pop() {
+        let node = tree
+            .tree
+            .node_weight(index)
+            .ok_or_else(|| eyre!("node not found"))?;
+        write_json(writer, &FlatNode { node, parent })?;
+
+        for child in tree.children_of(index).into_iter().rev() {
+            stack.push((child, Some(node.taxon.taxonomy_id)));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::fixtures::tree;
+    use libspideog::{
+        kraken::{Fragments, Taxon},
+        taxonomy::Rank,
+    };
+    use std::iter::FromIterator;
+
+    #[test]
+    fn test_write_json_tree() {
+        let mut output = Vec::new();
+        write_json(&mut output, &tree()).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        pretty_assertions::assert_eq!(json["taxid"], 1);
+        pretty_assertions::assert_eq!(json["reads"]["clade_count_reads"], 10);
+        pretty_assertions::assert_eq!(json["children"][0]["name"], "Bacteria");
+        pretty_assertions::assert_eq!(json["children"][0]["reads"], serde_json::Value::Null);
+        pretty_assertions::assert_eq!(json["children"][0]["children"][0]["indent"], 2);
+        pretty_assertions::assert_eq!(json["children"][1]["rank"], "Domain_0");
+    }
+
+    #[test]
+    fn test_write_ndjson_tree() {
+        let mut output = Vec::new();
+        write_ndjson_tree(&mut output, &tree()).unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let parents: Vec<(u64, Option<u64>)> = lines
+            .iter()
+            .map(|line| (line["taxid"].as_u64().unwrap(), line["parent"].as_u64()))
+            .collect();
+
+        pretty_assertions::assert_eq!(
+            parents,
+            vec![(1, None), (2, Some(1)), (562, Some(2)), (2157, Some(1))]
+        );
+    }
+
+    #[test]
+    fn test_write_ndjson_samples() {
+        let taxon = Taxon::new(562, "Escherichia coli", Rank::Species(0));
+        let fragments = Fragments {
+            clade_count_reads: 3,
+            taxon_count_reads: 2,
+            ..Fragments::default()
+        };
+        let samples = Samples::from_iter(vec![
+            (
+                "first".to_string(),
+                vec![(taxon.clone(), fragments.clone())]
+                    .into_iter()
+                    .collect(),
+            ),
+            (
+                "second".to_string(),
+                vec![(taxon, fragments)].into_iter().collect(),
+            ),
+        ]);
+
+        let mut output = Vec::new();
+        write_ndjson_samples(&mut output, &samples).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        pretty_assertions::assert_eq!(output.lines().count(), 2);
+        let row: serde_json::Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+        pretty_assertions::assert_eq!(row["sample"], "first");
+        pretty_assertions::assert_eq!(row["taxid"], 562);
+        pretty_assertions::assert_eq!(row["rank"], "Species_0");
+        pretty_assertions::assert_eq!(row["clade_count_reads"], 3);
+        pretty_assertions::assert_eq!(row["taxon_count_reads"], 2);
+    }
+}
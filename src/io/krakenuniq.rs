@@ -0,0 +1,28 @@
+use std::io::Read;
+
+use csv::Reader;
+use libspideog::{
+    errors::SpideogError,
+    kraken::krakenuniq::{KrakenUniqRanks, KrakenUniqReportRecord},
+};
+use tracing::instrument;
+
+use super::report::FromKrakenRecords;
+
+/// Build a dataset from a KrakenUniq report (header line required)
+pub trait ParseKrakenUniqReport: Sized {
+    fn parse_krakenuniq<R: Read>(reader: &mut Reader<R>) -> Result<Self, SpideogError>;
+}
+
+impl<T: FromKrakenRecords> ParseKrakenUniqReport for T {
+    #[instrument(skip(reader))]
+    fn parse_krakenuniq<R: Read>(reader: &mut Reader<R>) -> Result<Self, SpideogError> {
+        let mut ranks = KrakenUniqRanks::new();
+
+        Self::from_records(reader.deserialize().map(
+            |result: Result<KrakenUniqReportRecord, csv::Error>| {
+                ranks.resolve(result.map_err(SpideogError::KrakenParser)?)
+            },
+        ))
+    }
+}
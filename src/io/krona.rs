@@ -0,0 +1,404 @@
+use color_eyre::{Help, Report};
+use daggy::NodeIndex;
+use eyre::Context;
+use libspideog::{
+    data::tree::{Reads, Tree},
+    taxonomy::index::ROOT_TAXONOMY_ID,
+};
+use std::io;
+
+use super::xml::escape;
+
+/// Base URL of the Krona resources loaded by the HTML charts
+const KRONA_URL: &str = "https://marbl.github.io/Krona";
+
+/// Node of a Krona chart, with one value per dataset
+#[derive(Debug, Default)]
+struct KronaNode {
+    name: String,
+    taxonomy_id: Option<u64>,
+    rank: Option<String>,
+    /// reads assigned to the taxon in each dataset
+    assigned: Vec<u64>,
+    /// reads assigned to the clade in the reports, `0` when unknown
+    clade: Vec<u64>,
+    /// reads of the clade, computed by [`KronaNode::compute_totals`]
+    totals: Vec<u64>,
+    children: Vec<KronaNode>,
+}
+
+impl KronaNode {
+    fn new(name: String, datasets: usize) -> Self {
+        Self {
+            name,
+            assigned: vec![0; datasets],
+            clade: vec![0; datasets],
+            ..Self::default()
+        }
+    }
+
+    fn add(&mut self, dataset: usize, reads: Option<Reads>) {
+        let reads = reads.unwrap_or_default();
+        self.assigned[dataset] += reads.taxon;
+        self.clade[dataset] += reads.clade;
+    }
+
+    fn child(&mut self, tree: &Tree, index: NodeIndex, datasets: usize) -> Option<&mut Self> {
+        let node = tree.tree.node_weight(index)?;
+        let taxonomy_id = node.taxon.taxonomy_id;
+
+        let position = match self
+            .children
+            .iter()
+            .position(|child| child.taxonomy_id == Some(taxonomy_id))
+        {
+            Some(position) => position,
+            None => {
+                let mut child = Self::new(node.taxon.name.clone(), datasets);
+                child.taxonomy_id = Some(taxonomy_id);
+                child.rank = Some(node.taxon.taxonomy_level.to_string());
+                self.children.push(child);
+                self.children.len() - 1
+            }
+        };
+
+        self.children.get_mut(position)
+    }
+
+    /// Add the node of a tree and its descendants to the chart. The root is the center of the chart
+    /// and the unclassified reads are a child of the center.
+    fn merge(&mut self, tree: &Tree, index: NodeIndex, dataset: usize, datasets: usize) {
+        let node = match tree.tree.node_weight(index) {
+            Some(node) => node,
+            None => return,
+        };
+        let taxonomy_id = node.taxon.taxonomy_id;
+
+        let parent = if taxonomy_id == ROOT_TAXONOMY_ID {
+            self.add(dataset, node.reads);
+            self
+        } else if taxonomy_id == 0 {
+            // the unclassified line is the origin of the trees built from Kraken reports
+            if let Some(unclassified) = self.child(tree, index, datasets) {
+                unclassified.add(dataset, node.reads);
+            }
+            self
+        } else {
+            match self.child(tree, index, datasets) {
+                Some(child) => {
+                    child.add(dataset, node.reads);
+                    child
+                }
+                None => return,
+            }
+        };
+
+        for child in tree.children_of(index) {
+            parent.merge(tree, child, dataset, datasets);
+        }
+    }
+
+    /// Reads of the clades: the clade reads of the reports, or the sum of the assigned reads and the
+    /// reads of the children when it is larger (e.g. for the ancestors added from a taxonomy)
+    fn compute_totals(&mut self) {
+        for child in &mut self.children {
+            child.compute_totals();
+        }
+
+        self.totals = (0..self.assigned.len())
+            .map(|dataset| {
+                let children: u64 = self.children.iter().map(|c| c.totals[dataset]).sum();
+                self.clade[dataset].max(self.assigned[dataset] + children)
+            })
+            .collect();
+    }
+
+    /// Reads of the node not assigned to one of its children
+    fn unassigned(&self, dataset: usize) -> u64 {
+        let children: u64 = self.children.iter().map(|c| c.totals[dataset]).sum();
+        self.totals[dataset].saturating_sub(children)
+    }
+}
+
+/// Krona chart, with one dataset per sample
+#[derive(Debug)]
+pub struct KronaChart {
+    datasets: Vec<String>,
+    root: KronaNode,
+}
+
+impl KronaChart {
+    pub fn from_trees<'a, I>(samples: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, &'a Tree)>,
+    {
+        let samples: Vec<(&str, &Tree)> = samples.into_iter().collect();
+        let datasets = samples.len();
+        let mut root = KronaNode::new(String::from("Root"), datasets);
+
+        for (dataset, (_, tree)) in samples.iter().enumerate() {
+            if let Some(origin) = tree.origin {
+                root.merge(tree, origin, dataset, datasets);
+            }
+        }
+
+        root.compute_totals();
+
+        Self {
+            datasets: samples
+                .iter()
+                .map(|(name, _)| (*name).to_string())
+                .collect(),
+            root,
+        }
+    }
+}
+
+/// Write a chart in the text format of `ktImportText`: the reads, then the lineage, separated by tabs
+pub fn write_krona_text<W: io::Write>(writer: &mut W, chart: &KronaChart) -> Result<(), Report> {
+    if chart.datasets.len() > 1 {
+        return Err(eyre!(
+            "the Krona text format holds only one sample, found {}",
+            chart.datasets.len()
+        ))
+        .suggestion(
+            "use `--format krona-html`, or convert the reports one by one with `convert-tree`",
+        );
+    }
+
+    let mut stack: Vec<(&KronaNode, Vec<&str>)> = vec![(&chart.root, Vec::new())];
+
+    while let Some((node, lineage)) = stack.pop() {
+        let reads = node.unassigned(0);
+        if reads > 0 {
+            write!(writer, "{}", reads)?;
+            for name in &lineage {
+                write!(writer, "\t{}", name)?;
+            }
+            writeln!(writer)?;
+        }
+
+        for child in node.children.iter().rev() {
+            let mut child_lineage = lineage.clone();
+            child_lineage.push(&child.name);
+            stack.push((child, child_lineage));
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a Krona HTML chart. The Krona JavaScript is embedded when it is given, otherwise it is
+/// loaded from the Krona website; the icons are always loaded from the Krona website.
+pub fn write_krona_html<W: io::Write>(
+    writer: &mut W,
+    chart: &KronaChart,
+    script: Option<&str>,
+) -> Result<(), Report> {
+    writeln!(
+        writer,
+        r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd">"#
+    )?;
+    writeln!(
+        writer,
+        r#"<html xmlns="http://www.w3.org/1999/xhtml" xml:lang="en" lang="en">"#
+    )?;
+    writeln!(writer, " <head>")?;
+    writeln!(writer, r#"  <meta charset="utf-8"/>"#)?;
+    writeln!(
+        writer,
+        r#"  <link rel="shortcut icon" href="{}/img/favicon.ico"/>"#,
+        KRONA_URL
+    )?;
+    match script {
+        Some(script) => writeln!(writer, "  <script>\n{}\n  </script>", script)?,
+        None => {
+            writeln!(
+                writer,
+                r#"  <script id="notfound">window.onload=function(){{document.body.innerHTML="Could not get resources from \"{}\"."}}</script>"#,
+                KRONA_URL
+            )?;
+            writeln!(
+                writer,
+                r#"  <script src="{}/src/krona-2.0.js"></script>"#,
+                KRONA_URL
+            )?;
+        }
+    }
+    writeln!(writer, " </head>")?;
+    writeln!(writer, " <body>")?;
+    writeln!(
+        writer,
+        r#"  <img id="hiddenImage" src="{}/img/hidden.png" style="display:none"/>"#,
+        KRONA_URL
+    )?;
+    writeln!(
+        writer,
+        r#"  <img id="loadingImage" src="{}/img/loading.gif" style="display:none"/>"#,
+        KRONA_URL
+    )?;
+    writeln!(
+        writer,
+        "  <noscript>Javascript must be enabled to view this page.</noscript>"
+    )?;
+    writeln!(writer, r#"  <div style="display:none">"#)?;
+    writeln!(writer, r#"  <krona collapse="true" key="true">"#)?;
+    writeln!(writer, r#"   <attributes magnitude="magnitude">"#)?;
+    writeln!(writer, "    <list>members</list>")?;
+    writeln!(
+        writer,
+        r#"    <attribute display="Total">magnitude</attribute>"#
+    )?;
+    writeln!(
+        writer,
+        r#"    <attribute display="Taxon" mono="true" hrefBase="https://www.ncbi.nlm.nih.gov/Taxonomy/Browser/wwwtax.cgi?mode=Info&amp;id=">taxid</attribute>"#
+    )?;
+    writeln!(
+        writer,
+        r#"    <attribute display="Rank" mono="true">rank</attribute>"#
+    )?;
+    writeln!(writer, "   </attributes>")?;
+    writeln!(writer, "   <datasets>")?;
+    for dataset in &chart.datasets {
+        writeln!(writer, "    <dataset>{}</dataset>", escape(dataset))?;
+    }
+    writeln!(writer, "   </datasets>")?;
+
+    write_krona_node(writer, &chart.root, 0)?;
+
+    writeln!(writer, "  </krona>")?;
+    writeln!(writer, "  </div>")?;
+    writeln!(writer, " </body>")?;
+    writeln!(writer, "</html>")?;
+
+    Ok(())
+}
+
+fn write_values<W, T>(writer: &mut W, tag: &str, values: &[T]) -> Result<(), io::Error>
+where
+    W: io::Write,
+    T: std::fmt::Display,
+{
+    write!(writer, "<{}>", tag)?;
+    for value in values {
+        write!(writer, "<val>{}</val>", value)?;
+    }
+    write!(writer, "</{}>", tag)
+}
+
+fn write_krona_node<W: io::Write>(
+    writer: &mut W,
+    node: &KronaNode,
+    depth: usize,
+) -> Result<(), Report> {
+    let indent = " ".repeat(depth + 3);
+    let datasets = node.totals.len();
+
+    write!(writer, r#"{}<node name="{}">"#, indent, escape(&node.name))?;
+    write_values(writer, "magnitude", &node.totals)?;
+    if let Some(taxonomy_id) = node.taxonomy_id {
+        write_values(writer, "taxid", &vec![taxonomy_id; datasets])?;
+    }
+    if let Some(rank) = &node.rank {
+        write_values(writer, "rank", &vec![escape(rank); datasets])?;
+    }
+    writeln!(writer)?;
+
+    for child in &node.children {
+        write_krona_node(writer, child, depth + 1)?;
+    }
+
+    writeln!(writer, "{}</node>", indent)
+        .wrap_err_with(|| format!("failed to write the node of `{}`", node.name))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::fixtures::node;
+    use libspideog::taxonomy::Rank;
+
+    /// Tree as parsed from a Kraken report: unclassified, then the root as its child
+    fn tree(ecoli: u64) -> Tree {
+        let mut tree = Tree::new();
+        tree.with_origin(node(
+            0,
+            "unclassified",
+            Rank::Unclassified(0),
+            0,
+            Some((5, 5)),
+        ));
+        tree.child(
+            NodeIndex::new(0),
+            node(1, "root", Rank::Root(0), 0, Some((10 + ecoli, 1))),
+        );
+        tree.child(
+            NodeIndex::new(1),
+            node(2, "Bacteria", Rank::Domain(0), 1, Some((9 + ecoli, 9))),
+        );
+        tree.child(
+            NodeIndex::new(2),
+            node(
+                562,
+                "Escherichia coli",
+                Rank::Species(0),
+                3,
+                Some((ecoli, ecoli)),
+            ),
+        );
+        tree
+    }
+
+    #[test]
+    fn test_write_krona_text() {
+        let tree = tree(4);
+        let chart = KronaChart::from_trees(vec![("sample", &tree)]);
+
+        let mut output = Vec::new();
+        write_krona_text(&mut output, &chart).unwrap();
+
+        pretty_assertions::assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1\n5\tunclassified\n9\tBacteria\n4\tBacteria\tEscherichia coli\n"
+        );
+    }
+
+    #[test]
+    fn test_write_krona_text_multiple_samples() {
+        let (first, second) = (tree(4), tree(0));
+        let chart = KronaChart::from_trees(vec![("first", &first), ("second", &second)]);
+
+        assert!(write_krona_text(&mut Vec::new(), &chart).is_err());
+    }
+
+    #[test]
+    fn test_krona_chart_datasets() {
+        let (first, second) = (tree(4), tree(0));
+        let chart = KronaChart::from_trees(vec![("first", &first), ("second", &second)]);
+
+        pretty_assertions::assert_eq!(chart.root.totals, vec![19, 15]);
+        let bacteria = &chart.root.children[1];
+        pretty_assertions::assert_eq!(bacteria.name, "Bacteria");
+        pretty_assertions::assert_eq!(bacteria.totals, vec![13, 9]);
+        pretty_assertions::assert_eq!(bacteria.children[0].totals, vec![4, 0]);
+
+        let mut output = Vec::new();
+        write_krona_html(&mut output, &chart, None).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("<dataset>first</dataset>"));
+        assert!(output.contains(r#"<script src="https://marbl.github.io/Krona/src/krona-2.0.js">"#));
+        assert!(output.contains(
+            r#"<node name="Escherichia coli"><magnitude><val>4</val><val>0</val></magnitude><taxid><val>562</val><val>562</val></taxid>"#
+        ));
+
+        let mut output = Vec::new();
+        write_krona_html(&mut output, &chart, Some("var krona = true;")).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("  <script>\nvar krona = true;\n  </script>"));
+        assert!(!output.contains("krona-2.0.js"));
+    }
+}
@@ -0,0 +1,34 @@
+use std::io::Read;
+
+use csv::Reader;
+use libspideog::{
+    errors::SpideogError,
+    kraken::KrakenRecord,
+    metaphlan::{to_report, MetaphlanRecord},
+};
+use tracing::instrument;
+
+use super::report::FromKrakenRecords;
+
+/// Build a dataset from a MetaPhlAn profile (`#` header lines are skipped)
+pub trait ParseMetaphlanProfile: Sized {
+    fn parse_metaphlan<R: Read>(reader: &mut Reader<R>) -> Result<Self, SpideogError>;
+}
+
+impl<T: FromKrakenRecords> ParseMetaphlanProfile for T {
+    #[instrument(skip(reader))]
+    fn parse_metaphlan<R: Read>(reader: &mut Reader<R>) -> Result<Self, SpideogError> {
+        let records = reader
+            .records()
+            .map(|result| {
+                MetaphlanRecord::from_string_record(&result.map_err(SpideogError::KrakenParser)?)
+            })
+            .collect::<Result<Vec<MetaphlanRecord>, SpideogError>>()?;
+
+        Self::from_records(
+            to_report(&records)?
+                .into_iter()
+                .map(|record| Ok(KrakenRecord::Standard(record))),
+        )
+    }
+}
@@ -0,0 +1,281 @@
+use color_eyre::Report;
+use daggy::NodeIndex;
+use libspideog::{
+    data::{
+        abundance::{SampleName, Samples},
+        tree::Tree,
+    },
+    kraken::Taxon,
+    taxonomy::Rank,
+};
+use std::{collections::HashMap, io};
+
+use super::{abundance_csv::WideValue, AbundanceValue};
+
+/// Prefix of a taxon in mpa lineages, `x` for the intermediate ranks. The root and the unclassified
+/// reads have no prefix, they are not part of the lineages.
+fn prefix(rank: Rank) -> Option<char> {
+    match rank {
+        Rank::Unclassified(_) | Rank::Root(0) => None,
+        Rank::Root(_) => Some('x'),
+        rank if rank.offset() == 0 => Some(rank.letter().to_ascii_lowercase()),
+        _ => Some('x'),
+    }
+}
+
+/// Lineage in the mpa style of `kreport2mpa.py`, e.g. `d__Bacteria|p__Proteobacteria`. `None` when the
+/// taxon itself is not written, i.e. the root, the unclassified reads, or an intermediate rank when
+/// only the canonical ranks are kept.
+pub fn mpa_lineage(lineage: &[Taxon], canonical_ranks: bool) -> Option<String> {
+    let last = lineage.last()?;
+    match prefix(last.taxonomy_level) {
+        None => return None,
+        Some('x') if canonical_ranks => return None,
+        Some(_) => {}
+    }
+
+    let names: Vec<String> = lineage
+        .iter()
+        .filter_map(|taxon| {
+            prefix(taxon.taxonomy_level)
+                .filter(|prefix| !(canonical_ranks && *prefix == 'x'))
+                .map(|prefix| format!("{}__{}", prefix, taxon.name.replace(' ', "_")))
+        })
+        .collect();
+
+    Some(names.join("|"))
+}
+
+/// Reads of the clades in the mpa lineages, with one column per sample (see `combine_mpa.py`)
+#[derive(Debug, Default)]
+pub struct MpaTable {
+    samples: Vec<SampleName>,
+    rows: Vec<(String, Vec<f64>)>,
+    index: HashMap<String, usize>,
+}
+
+impl MpaTable {
+    fn new(samples: Vec<SampleName>) -> Self {
+        Self {
+            samples,
+            ..Self::default()
+        }
+    }
+
+    fn add(&mut self, lineage: String, sample: usize, value: f64) {
+        let samples = self.samples.len();
+        let row = match self.index.get(&lineage) {
+            Some(row) => *row,
+            None => {
+                self.index.insert(lineage.clone(), self.rows.len());
+                self.rows.push((lineage, vec![0.0; samples]));
+                self.rows.len() - 1
+            }
+        };
+
+        self.rows[row].1[sample] += value;
+    }
+
+    /// Lineages in the order of the trees, the nodes added from a taxonomy have the reads of their children
+    #[allow(clippy::cast_precision_loss)]
+    pub fn from_trees(samples: &[(SampleName, Tree)], canonical_ranks: bool) -> Self {
+        let mut table = Self::new(samples.iter().map(|(name, _)| name.clone()).collect());
+
+        for (sample, (_, tree)) in samples.iter().enumerate() {
+            let mut stack: Vec<(NodeIndex, Vec<Taxon>)> = tree
+                .origin
+                .map(|origin| (origin, Vec::new()))
+                .into_iter()
+                .collect();
+
+            while let Some((index, mut lineage)) = stack.pop() {
+                let node = match tree.tree.node_weight(index) {
+                    Some(node) => node,
+                    None => continue,
+                };
+                lineage.push(node.taxon.clone());
+
+                if let Some(mpa) = mpa_lineage(&lineage, canonical_ranks) {
+                    table.add(mpa, sample, clade_reads(tree, index) as f64);
+                }
+
+                for child in tree.children_of(index).into_iter().rev() {
+                    stack.push((child, lineage.clone()));
+                }
+            }
+        }
+
+        table
+    }
+
+    /// Lineages sorted alphabetically, the clade reads (or the new estimated reads of Bracken) are written
+    pub fn from_samples<V: WideValue>(samples: &Samples<V>, canonical_ranks: bool) -> Self {
+        let mut table = Self::new(samples.data.iter().map(|s| s.name.clone()).collect());
+
+        let mut lineages: Vec<(String, &Taxon)> = samples
+            .unique_taxons
+            .iter()
+            .filter_map(|taxon| {
+                let lineage = samples
+                    .lineage(taxon)
+                    .map_or_else(|| vec![taxon.clone()], Clone::clone);
+                mpa_lineage(&lineage, canonical_ranks).map(|mpa| (mpa, taxon))
+            })
+            .collect();
+        lineages.sort();
+
+        for (mpa, taxon) in lineages {
+            for (sample, abundance) in samples.data.iter().enumerate() {
+                let value = abundance
+                    .dataset
+                    .get(taxon)
+                    .map_or(0.0, |values| values.wide_value(AbundanceValue::CladeReads));
+                table.add(mpa.clone(), sample, value);
+            }
+        }
+
+        table
+    }
+
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Report> {
+        write!(writer, "#Classification")?;
+        for sample in &self.samples {
+            write!(writer, "\t{}", sample)?;
+        }
+        writeln!(writer)?;
+
+        for (lineage, values) in &self.rows {
+            write!(writer, "{}", lineage)?;
+            for value in values {
+                write!(writer, "\t{}", value)?;
+            }
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads of a clade, or the sum of the reads of the children for the nodes without reads
+fn clade_reads(tree: &Tree, index: NodeIndex) -> u64 {
+    match tree.tree.node_weight(index).and_then(|node| node.reads) {
+        Some(reads) => reads.clade,
+        None => tree
+            .children_of(index)
+            .into_iter()
+            .map(|child| clade_reads(tree, child))
+            .sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libspideog::{
+        data::{abundance::AbundanceData, tree::IndentedTaxon, tree::Reads},
+        kraken::Fragments,
+    };
+    use std::iter::FromIterator;
+    use test_case::test_case;
+
+    fn lineage() -> Vec<Taxon> {
+        vec![
+            Taxon::new(1, "root", Rank::Root(0)),
+            Taxon::new(131_567, "cellular organisms", Rank::Root(1)),
+            Taxon::new(2, "Bacteria", Rank::Domain(0)),
+            Taxon::new(1224, "Proteobacteria", Rank::Phylum(0)),
+            Taxon::new(562, "Escherichia coli", Rank::Species(0)),
+            Taxon::new(83333, "Escherichia coli K-12", Rank::Species(1)),
+        ]
+    }
+
+    #[test_case(5, false => Some("x__cellular_organisms|d__Bacteria|p__Proteobacteria|s__Escherichia_coli".to_string()))]
+    #[test_case(5, true => Some("d__Bacteria|p__Proteobacteria|s__Escherichia_coli".to_string()))]
+    #[test_case(6, false => Some("x__cellular_organisms|d__Bacteria|p__Proteobacteria|s__Escherichia_coli|x__Escherichia_coli_K-12".to_string()))]
+    #[test_case(6, true => None)]
+    #[test_case(1, false => None)]
+    fn test_mpa_lineage(length: usize, canonical_ranks: bool) -> Option<String> {
+        mpa_lineage(&lineage()[..length], canonical_ranks)
+    }
+
+    #[test]
+    fn test_mpa_table_from_trees() {
+        let node =
+            |(taxonomy_id, name, taxonomy_level): (u64, &str, Rank), indent, reads| IndentedTaxon {
+                indent,
+                taxon: Taxon::new(taxonomy_id, name, taxonomy_level),
+                reads,
+            };
+        let reads = |clade| Some(Reads { clade, taxon: 0 });
+
+        let mut tree = Tree::new();
+        tree.with_origin(node(
+            (0, "unclassified", Rank::Unclassified(0)),
+            0,
+            reads(5),
+        ));
+        tree.child(
+            NodeIndex::new(0),
+            node((1, "root", Rank::Root(0)), 0, reads(10)),
+        );
+        tree.child(
+            NodeIndex::new(1),
+            node((2, "Bacteria", Rank::Domain(0)), 1, None),
+        );
+        tree.child(
+            NodeIndex::new(2),
+            node((1224, "Proteobacteria", Rank::Phylum(0)), 2, reads(7)),
+        );
+        tree.child(
+            NodeIndex::new(2),
+            node((1239, "Firmicutes", Rank::Phylum(0)), 2, reads(3)),
+        );
+
+        let table = MpaTable::from_trees(&[("sample".to_string(), tree)], true);
+        let mut output = Vec::new();
+        table.write(&mut output).unwrap();
+
+        pretty_assertions::assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "#Classification\tsample\nd__Bacteria\t10\nd__Bacteria|p__Proteobacteria\t7\nd__Bacteria|p__Firmicutes\t3\n"
+        );
+    }
+
+    #[test]
+    fn test_mpa_table_from_samples() {
+        let lineage = lineage();
+        let data = |values: &[(usize, u64)]| {
+            let mut data = AbundanceData::new();
+            for (length, clade_count_reads) in values {
+                let taxon = lineage[length - 1].clone();
+                data.lineages
+                    .insert(taxon.clone(), lineage[..*length].to_vec());
+                data.insert(
+                    taxon,
+                    Fragments {
+                        clade_count_reads: *clade_count_reads,
+                        ..Fragments::default()
+                    },
+                );
+            }
+            data
+        };
+
+        let first = data(&[(3, 12), (5, 4)]);
+        let second = data(&[(3, 6)]);
+        let samples = Samples::from_iter(vec![
+            ("first".to_string(), first),
+            ("second".to_string(), second),
+        ]);
+
+        let mut output = Vec::new();
+        MpaTable::from_samples(&samples, true)
+            .write(&mut output)
+            .unwrap();
+
+        pretty_assertions::assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "#Classification\tfirst\tsecond\nd__Bacteria\t12\t6\nd__Bacteria|p__Proteobacteria|s__Escherichia_coli\t4\t0\n"
+        );
+    }
+}
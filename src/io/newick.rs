@@ -1,32 +1,182 @@
 use color_eyre::Report;
 use daggy::{NodeIndex, Walker};
 use eyre::ContextCompat;
-use libspideog::data::tree::Tree;
-use std::{borrow::Cow, io};
+use libspideog::{
+    data::{
+        abundance::SampleName,
+        tree::{IndentedTaxon, Reads, Tree},
+    },
+    kraken::Taxon,
+};
+use std::{borrow::Cow, collections::HashMap, io};
 use tracing::instrument;
 
-pub fn write_newick<W>(writer: &mut W, tree: &Tree) -> Result<(), Report>
+use super::{BranchLength, NewickLabel};
+
+/// Branch lengths and labels of the nodes
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    pub branch_length: BranchLength,
+    pub label: NewickLabel,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            branch_length: BranchLength::Indent,
+            label: NewickLabel::Clean,
+        }
+    }
+}
+
+impl Style {
+    fn label<'a>(&self, taxon: &'a Taxon) -> Cow<'a, str> {
+        match self.label {
+            NewickLabel::Clean => clean_name(taxon.name.as_str()),
+            NewickLabel::Quoted => Cow::Owned(quote_name(&taxon.name)),
+            NewickLabel::Taxid => Cow::Owned(taxon.taxonomy_id.to_string()),
+            NewickLabel::NameTaxid => Cow::Owned(format!(
+                "{}|{}",
+                clean_name(taxon.name.as_str()),
+                taxon.taxonomy_id
+            )),
+        }
+    }
+
+    pub fn distance(
+        &self,
+        node: &IndentedTaxon,
+        parent: Option<&IndentedTaxon>,
+    ) -> Result<Option<usize>, Report> {
+        let distance = match self.branch_length {
+            BranchLength::Indent => {
+                let parent_indent = parent.map_or(0, |parent| parent.indent);
+                node.indent.checked_sub(parent_indent).wrap_err_with(|| {
+                    format!(
+                        "failed to compute new distance: node {} - parent {}",
+                        node.indent, parent_indent
+                    )
+                })?
+            }
+            BranchLength::Rank => parent.map_or(0, |parent| {
+                node.taxon
+                    .taxonomy_level
+                    .steps_from(&parent.taxon.taxonomy_level) as usize
+            }),
+            BranchLength::One => usize::from(parent.is_some()),
+            BranchLength::None => return Ok(None),
+        };
+
+        Ok(Some(distance))
+    }
+
+    /// Length of the branch between a node and its parent as used by the diversity indices, zero
+    /// without branch lengths
+    #[allow(clippy::cast_precision_loss)]
+    pub fn branch_length(
+        &self,
+        node: &IndentedTaxon,
+        parent: &IndentedTaxon,
+    ) -> Result<f64, Report> {
+        self.distance(node, Some(parent))
+            .map(|distance| distance.unwrap_or(0) as f64)
+    }
+}
+
+pub fn write_newick<W>(writer: &mut W, tree: &Tree, style: &Style) -> Result<(), Report>
+where
+    W: std::io::Write,
+{
+    write_children_recursively(writer, tree, tree.origin.unwrap(), None, style, None)?; // TODO: add error / panic Tree not initilialised
+    write_end(writer)?;
+
+    Ok(())
+}
+
+/// Annotations of the extended Newick format (NHX), the trees combined from several samples also have
+/// the weight of the edges and the reads of each sample
+#[derive(Debug, Default)]
+pub struct Nhx<'a> {
+    combined: bool,
+    samples: Vec<(&'a str, HashMap<u64, Reads>)>,
+}
+
+impl<'a> Nhx<'a> {
+    #[must_use]
+    pub fn combined(samples: &'a [(SampleName, Tree)]) -> Self {
+        Self {
+            combined: true,
+            samples: samples
+                .iter()
+                .map(|(name, tree)| (name.as_str(), tree.reads_by_taxonomy_id()))
+                .collect(),
+        }
+    }
+
+    fn format_comment(&self, tree: &Tree, node: NodeIndex) -> Result<String, Report> {
+        let node_data = tree.tree.node_weight(node).wrap_err("node not found")?;
+        let taxonomy_id = node_data.taxon.taxonomy_id;
+
+        let mut comment = format!(
+            "[&&NHX:taxid={}:rank={}",
+            taxonomy_id, node_data.taxon.taxonomy_level
+        );
+        if let Some(reads) = node_data.reads {
+            comment.push_str(&format!(":reads={}", reads.clade));
+        }
+        if self.combined {
+            if let Some(weight) = tree.parent_edge_weight(node) {
+                comment.push_str(&format!(":weight={}", weight));
+            }
+        }
+        for (sample, reads) in &self.samples {
+            let reads = reads.get(&taxonomy_id).map_or(0, |reads| reads.clade);
+            comment.push_str(&format!(":reads_{}={}", nhx_key(sample), reads));
+        }
+        comment.push(']');
+
+        Ok(comment)
+    }
+}
+
+pub fn write_nhx<W>(writer: &mut W, tree: &Tree, style: &Style, nhx: &Nhx) -> Result<(), Report>
 where
     W: std::io::Write,
 {
-    write_children_recursively(writer, tree, tree.origin.unwrap(), 0)?; // TODO: add error / panic Tree not initilialised
+    let origin = tree.origin.wrap_err("tree not initialized")?;
+    write_children_recursively(writer, tree, origin, None, style, Some(nhx))?;
     write_end(writer)?;
 
     Ok(())
 }
 
+/// NHX keys and values cannot contain the separators of the comments
+fn nhx_key(input: &str) -> String {
+    input
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
 #[inline]
-pub fn write_name_distance<W, S>(writer: &mut W, name: S, distance: usize) -> Result<(), io::Error>
+pub fn write_name_distance<W, S>(
+    writer: &mut W,
+    label: S,
+    distance: Option<usize>,
+) -> Result<(), io::Error>
 where
     W: io::Write,
     S: AsRef<str>,
 {
-    write!(writer, "{}", format_name_distance(name, distance))
+    write!(writer, "{}", format_name_distance(label, distance))
 }
 
 #[inline]
-fn format_name_distance<S: AsRef<str>>(name: S, distance: usize) -> String {
-    format!("{}:{}", clean_name(name.as_ref()), distance)
+fn format_name_distance<S: AsRef<str>>(label: S, distance: Option<usize>) -> String {
+    match distance {
+        Some(distance) => format!("{}:{}", label.as_ref(), distance),
+        None => label.as_ref().to_string(),
+    }
 }
 
 #[inline]
@@ -59,7 +209,7 @@ pub fn clean_name<'a, S: Into<Cow<'a, str>>>(input: S) -> Cow<'a, str> {
         let rest = input[first_trouble_character..].chars();
         for c in rest {
             match c {
-                ' ' | '-' | '/' | ':' => output.push_str("_"),
+                ' ' | '-' | '/' | ':' => output.push('_'),
                 '.' | ',' | '[' | ']' | '(' | ')' | '\'' | '\"' => {}
                 _ => output.push(c),
             }
@@ -70,11 +220,18 @@ pub fn clean_name<'a, S: Into<Cow<'a, str>>>(input: S) -> Cow<'a, str> {
     }
 }
 
+/// Quoted Newick label keeping the name unchanged, the single quotes are doubled
+fn quote_name(input: &str) -> String {
+    format!("'{}'", input.replace('\'', "''"))
+}
+
 pub fn write_children_recursively<W>(
     writer: &mut W,
     tree: &Tree,
     node: NodeIndex,
-    parent_indent: usize,
+    parent: Option<&IndentedTaxon>,
+    style: &Style,
+    nhx: Option<&Nhx>,
 ) -> Result<(), Report>
 where
     W: io::Write,
@@ -86,25 +243,18 @@ where
     }
 
     let node_data = tree.tree.node_weight(node).wrap_err("node not found")?;
-    let distance = node_data
-        .indent
-        .checked_sub(parent_indent)
-        .wrap_err_with(|| {
-            format!(
-                "failed to compute new distance: node {} - parent {}",
-                node_data.indent, parent_indent
-            )
-        })?;
+    let label = style.label(&node_data.taxon);
+    let distance = style.distance(node_data, parent)?;
 
     if children.is_empty() {
-        write_name_distance(writer, &node_data.taxon.name, distance)?;
+        write_name_distance(writer, &label, distance)?;
     } else {
         writer.write_all(b"(")?;
 
         let mut children_iter = children.iter().peekable();
 
         while let Some(node_id) = children_iter.next() {
-            write_children_recursively(writer, tree, *node_id, node_data.indent)?;
+            write_children_recursively(writer, tree, *node_id, Some(node_data), style, nhx)?;
 
             // not the last child, add a comma
             if children_iter.peek().is_some() {
@@ -114,7 +264,11 @@ where
 
         writer.write_all(b")")?;
 
-        write_name_distance(writer, &node_data.taxon.name, distance)?;
+        write_name_distance(writer, &label, distance)?;
+    }
+
+    if let Some(nhx) = nhx {
+        writer.write_all(nhx.format_comment(tree, node)?.as_bytes())?;
     }
 
     Ok(())
@@ -123,14 +277,108 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::io::fixtures::node;
+    use libspideog::taxonomy::Rank;
     use test_case::test_case;
 
     #[test_case(&("Homo sapiens", 2), "Homo_sapiens:2")]
     #[test_case(&("Bacteroidetes/Chlorobi group", 1), "Bacteroidetes_Chlorobi_group:1")]
     fn test_format_name_distance<S: AsRef<str>>(input: &(S, usize), expected: S) {
         assert_eq!(
-            format_name_distance(input.0.as_ref(), input.1),
+            format_name_distance(clean_name(input.0.as_ref()), Some(input.1)),
             expected.as_ref()
         );
     }
+
+    #[test_case(NewickLabel::Clean => "Bacteroidetes_Chlorobi_group")]
+    #[test_case(NewickLabel::Quoted => "'Bacteroidetes/Chlorobi group'")]
+    #[test_case(NewickLabel::Taxid => "68336")]
+    #[test_case(NewickLabel::NameTaxid => "Bacteroidetes_Chlorobi_group|68336")]
+    fn test_style_label(label: NewickLabel) -> String {
+        let style = Style {
+            label,
+            ..Style::default()
+        };
+        let node = node(
+            68336,
+            "Bacteroidetes/Chlorobi group",
+            Rank::Phylum(1),
+            3,
+            None,
+        );
+        style.label(&node.taxon).into_owned()
+    }
+
+    #[test_case("Saccharomyces cerevisiae 'S288C'" => "'Saccharomyces cerevisiae ''S288C'''")]
+    fn test_quote_name(input: &str) -> String {
+        quote_name(input)
+    }
+
+    #[test_case(BranchLength::Indent, true => Some(3))]
+    #[test_case(BranchLength::Indent, false => Some(5))]
+    #[test_case(BranchLength::Rank, true => Some(2))]
+    #[test_case(BranchLength::Rank, false => Some(0))]
+    #[test_case(BranchLength::One, true => Some(1))]
+    #[test_case(BranchLength::None, true => None)]
+    fn test_style_distance(branch_length: BranchLength, with_parent: bool) -> Option<usize> {
+        let style = Style {
+            branch_length,
+            ..Style::default()
+        };
+        let parent = node(2, "Bacteria", Rank::Domain(0), 2, None);
+        let node = node(1224, "Proteobacteria", Rank::Phylum(0), 5, None);
+        style
+            .distance(&node, Some(&parent).filter(|_| with_parent))
+            .unwrap()
+    }
+
+    #[test_case(BranchLength::Indent => 3.0)]
+    #[test_case(BranchLength::None => 0.0)]
+    fn test_style_branch_length(branch_length: BranchLength) -> f64 {
+        let style = Style {
+            branch_length,
+            ..Style::default()
+        };
+        let parent = node(2, "Bacteria", Rank::Domain(0), 2, None);
+        let node = node(1224, "Proteobacteria", Rank::Phylum(0), 5, None);
+        style.branch_length(&node, &parent).unwrap()
+    }
+
+    #[test_case("sample_2" => "sample_2")]
+    #[test_case("my sample:v1" => "my_sample_v1")]
+    fn test_nhx_key(input: &str) -> String {
+        nhx_key(input)
+    }
+
+    #[test]
+    fn test_write_nhx_combined() {
+        let mut first = Tree::new();
+        first.with_origin(node(1, "root", Rank::Root(0), 0, Some((10, 0))));
+        first.child(
+            NodeIndex::new(0),
+            node(562, "Escherichia coli", Rank::Species(0), 2, Some((4, 0))),
+        );
+        let mut second = Tree::new();
+        second.with_origin(node(1, "root", Rank::Root(0), 0, Some((3, 0))));
+
+        let samples = vec![
+            ("first".to_string(), first.clone()),
+            ("second".to_string(), second.clone()),
+        ];
+        let combined = first.try_combine_with(second).unwrap();
+
+        let mut output = Vec::new();
+        write_nhx(
+            &mut output,
+            &combined,
+            &Style::default(),
+            &Nhx::combined(&samples),
+        )
+        .unwrap();
+
+        pretty_assertions::assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "(Escherichia_coli:2[&&NHX:taxid=562:rank=Species_0:reads=4:weight=1:reads_first=4:reads_second=0])root:0[&&NHX:taxid=1:rank=Root_0:reads=13:reads_first=10:reads_second=3];\n"
+        );
+    }
 }
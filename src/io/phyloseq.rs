@@ -0,0 +1,357 @@
+use color_eyre::{Help, Report};
+use daggy::NodeIndex;
+use eyre::{Context, ContextCompat};
+use libspideog::{
+    data::{
+        abundance::{SampleName, Samples},
+        tree::Tree,
+    },
+    kraken::Taxon,
+    taxonomy::Rank,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
+
+use super::newick::write_end;
+
+/// Canonical ranks, the columns of the taxonomy table
+const RANKS: [(Rank, &str); 8] = [
+    (Rank::Domain(0), "Domain"),
+    (Rank::Kingdom(0), "Kingdom"),
+    (Rank::Phylum(0), "Phylum"),
+    (Rank::Class(0), "Class"),
+    (Rank::Order(0), "Order"),
+    (Rank::Family(0), "Family"),
+    (Rank::Genus(0), "Genus"),
+    (Rank::Species(0), "Species"),
+];
+
+/// Taxon of the OTU tables, identified by its taxid, with the names of its canonical ancestors
+#[derive(Debug)]
+pub struct Otu<'a> {
+    pub taxon: &'a Taxon,
+    pub ranks: [Option<&'a str>; 8],
+}
+
+/// A node is an OTU when it is a leaf or has reads assigned directly to it in at least one sample
+fn is_otu(tree: &Tree, index: NodeIndex) -> bool {
+    tree.children_of(index).is_empty()
+        || tree
+            .tree
+            .node_weight(index)
+            .and_then(|node| node.reads)
+            .is_some_and(|reads| reads.taxon > 0)
+}
+
+/// OTUs of a combined tree in depth-first order, their taxids are the OTU ids and must be unique
+pub fn otus(tree: &Tree) -> Result<Vec<Otu<'_>>, Report> {
+    let origin = tree.origin.wrap_err("tree not initialized")?;
+    let mut otus = Vec::new();
+    let mut taxids = HashSet::new();
+    let mut stack = vec![(origin, [None; 8])];
+
+    while let Some((index, mut ranks)) = stack.pop() {
+        let node = tree.tree.node_weight(index).wrap_err("node not found")?;
+        if let Some(column) = RANKS
+            .iter()
+            .position(|(rank, _)| *rank == node.taxon.taxonomy_level)
+        {
+            ranks[column] = Some(node.taxon.name.as_str());
+        }
+
+        if is_otu(tree, index) {
+            if !taxids.insert(node.taxon.taxonomy_id) {
+                return Err(eyre!(
+                    "taxid `{}` is shared by several OTUs, including `{}`",
+                    node.taxon.taxonomy_id,
+                    node.taxon.name
+                ))
+                .suggestion(
+                    "use reports with one taxid per taxon, MetaPhlAn 2 profiles have none",
+                );
+            }
+            otus.push(Otu {
+                taxon: &node.taxon,
+                ranks,
+            });
+        }
+
+        for child in tree.children_of(index).into_iter().rev() {
+            stack.push((child, ranks));
+        }
+    }
+
+    Ok(otus)
+}
+
+/// Reads assigned directly to each OTU (the taxon reads), one row per OTU and one column per sample
+pub fn write_otu_table<W: io::Write>(
+    writer: &mut W,
+    otus: &[Otu],
+    samples: &Samples,
+) -> Result<(), Report> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+
+    let reads: Vec<HashMap<u64, u64>> = samples
+        .data
+        .iter()
+        .map(|sample| {
+            sample
+                .dataset
+                .iter()
+                .map(|(taxon, fragments)| (taxon.taxonomy_id, fragments.taxon_count_reads))
+                .collect()
+        })
+        .collect();
+
+    let mut header = vec!["otu"];
+    header.extend(samples.data.iter().map(|sample| sample.name.as_str()));
+    csv_writer.write_record(&header)?;
+
+    for otu in otus {
+        let taxonomy_id = otu.taxon.taxonomy_id;
+        let mut record = vec![taxonomy_id.to_string()];
+        record.extend(
+            reads
+                .iter()
+                .map(|reads| reads.get(&taxonomy_id).copied().unwrap_or(0).to_string()),
+        );
+        csv_writer
+            .write_record(&record)
+            .wrap_err_with(|| format!("failed to write the reads of `{}`", otu.taxon.name))?;
+    }
+
+    csv_writer.flush()?;
+
+    Ok(())
+}
+
+/// Names of the canonical ancestors of each OTU, `NA` for the missing ranks
+pub fn write_tax_table<W: io::Write>(writer: &mut W, otus: &[Otu]) -> Result<(), Report> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+
+    let mut header = vec!["otu"];
+    header.extend(RANKS.iter().map(|(_, column)| *column));
+    csv_writer.write_record(&header)?;
+
+    for otu in otus {
+        let taxonomy_id = otu.taxon.taxonomy_id.to_string();
+        let mut record = vec![taxonomy_id.as_str()];
+        record.extend(otu.ranks.iter().map(|name| name.unwrap_or("NA")));
+        csv_writer
+            .write_record(&record)
+            .wrap_err_with(|| format!("failed to write the taxonomy of `{}`", otu.taxon.name))?;
+    }
+
+    csv_writer.flush()?;
+
+    Ok(())
+}
+
+/// Rows of a CSV table of sample metadata, reordered to match the samples. The first column has the
+/// sample names.
+pub fn write_sample_data<R: io::Read, W: io::Write>(
+    reader: R,
+    writer: &mut W,
+    samples: &[SampleName],
+) -> Result<(), Report> {
+    let mut csv_reader = csv::Reader::from_reader(reader);
+    let mut csv_writer = csv::Writer::from_writer(writer);
+
+    csv_writer.write_record(csv_reader.headers()?)?;
+
+    let mut records = HashMap::new();
+    for record in csv_reader.records() {
+        let record = record?;
+        if let Some(sample) = record.get(0) {
+            records.insert(sample.to_string(), record);
+        }
+    }
+
+    for sample in samples {
+        let record = records
+            .get(sample)
+            .ok_or_else(|| eyre!("sample `{}` not found in the sample data", sample))
+            .suggestion("the first column of the sample data must have the names of the reports without their extensions")?;
+        csv_writer.write_record(record)?;
+    }
+
+    csv_writer.flush()?;
+
+    Ok(())
+}
+
+/// Newick tree with the taxids of the OTUs as tip labels. The internal nodes are not labelled, the
+/// internal nodes that are also OTUs get an extra tip with a branch length of zero.
+pub fn write_otu_tree<W: io::Write>(writer: &mut W, tree: &Tree) -> Result<(), Report> {
+    let origin = tree.origin.wrap_err("tree not initialized")?;
+    write_otu_node(writer, tree, origin, 0)?;
+    write_end(writer)?;
+
+    Ok(())
+}
+
+fn write_otu_node<W: io::Write>(
+    writer: &mut W,
+    tree: &Tree,
+    index: NodeIndex,
+    parent_indent: usize,
+) -> Result<(), Report> {
+    let node = tree.tree.node_weight(index).wrap_err("node not found")?;
+    let distance = node.indent.checked_sub(parent_indent).wrap_err_with(|| {
+        format!(
+            "failed to compute new distance: node {} - parent {}",
+            node.indent, parent_indent
+        )
+    })?;
+
+    let children = tree.children_of(index);
+    if children.is_empty() {
+        write!(writer, "{}:{}", node.taxon.taxonomy_id, distance)?;
+        return Ok(());
+    }
+
+    writer.write_all(b"(")?;
+    for (position, child) in children.iter().enumerate() {
+        if position > 0 {
+            writer.write_all(b",")?;
+        }
+        write_otu_node(writer, tree, *child, node.indent)?;
+    }
+    if is_otu(tree, index) {
+        write!(writer, ",{}:0", node.taxon.taxonomy_id)?;
+    }
+    write!(writer, "):{}", distance)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::fixtures::node;
+    use libspideog::{data::abundance::AbundanceData, kraken::Fragments};
+    use std::iter::FromIterator;
+
+    fn tree() -> Tree {
+        let mut tree = Tree::new();
+        tree.with_origin(node(1, "root", Rank::Root(0), 0, Some((0, 0))));
+        tree.child(
+            NodeIndex::new(0),
+            node(2, "Bacteria", Rank::Domain(0), 1, Some((0, 5))),
+        );
+        tree.child(
+            NodeIndex::new(1),
+            node(1224, "Proteobacteria", Rank::Phylum(1), 2, Some((0, 0))),
+        );
+        tree.child(
+            NodeIndex::new(2),
+            node(562, "Escherichia coli", Rank::Species(0), 4, Some((0, 7))),
+        );
+        tree
+    }
+
+    #[test]
+    fn test_otus() {
+        let tree = tree();
+        let otus = otus(&tree).unwrap();
+
+        let taxids: Vec<u64> = otus.iter().map(|otu| otu.taxon.taxonomy_id).collect();
+        pretty_assertions::assert_eq!(taxids, vec![2, 562]);
+        pretty_assertions::assert_eq!(
+            otus[1].ranks,
+            [
+                Some("Bacteria"),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some("Escherichia coli")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_otus_duplicated_taxids() {
+        let mut tree = Tree::new();
+        tree.with_origin(node(1, "root", Rank::Root(0), 0, None));
+        tree.child(
+            NodeIndex::new(0),
+            node(0, "Escherichia coli", Rank::Species(0), 1, None),
+        );
+        tree.child(
+            NodeIndex::new(0),
+            node(0, "Staphylococcus aureus", Rank::Species(0), 1, None),
+        );
+
+        assert!(otus(&tree).is_err());
+    }
+
+    #[test]
+    fn test_write_otu_tree() {
+        let mut output = Vec::new();
+        write_otu_tree(&mut output, &tree()).unwrap();
+
+        pretty_assertions::assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "(((562:2):1,2:0):1):0;\n"
+        );
+    }
+
+    #[test]
+    fn test_write_otu_and_tax_tables() {
+        let tree = tree();
+        let otus = otus(&tree).unwrap();
+        let fragments = |taxon_count_reads| Fragments {
+            taxon_count_reads,
+            ..Fragments::default()
+        };
+        let first: AbundanceData = vec![(
+            Taxon::new(562, "Escherichia coli", Rank::Species(0)),
+            fragments(7),
+        )]
+        .into_iter()
+        .collect();
+        let second = vec![(Taxon::new(2, "Bacteria", Rank::Domain(0)), fragments(5))]
+            .into_iter()
+            .collect();
+        let samples = Samples::from_iter(vec![
+            ("first".to_string(), first),
+            ("second".to_string(), second),
+        ]);
+
+        let mut output = Vec::new();
+        write_otu_table(&mut output, &otus, &samples).unwrap();
+        pretty_assertions::assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "otu,first,second\n2,0,5\n562,7,0\n"
+        );
+
+        let mut output = Vec::new();
+        write_tax_table(&mut output, &otus).unwrap();
+        pretty_assertions::assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "otu,Domain,Kingdom,Phylum,Class,Order,Family,Genus,Species\n2,Bacteria,NA,NA,NA,NA,NA,NA,NA\n562,Bacteria,NA,NA,NA,NA,NA,NA,Escherichia coli\n"
+        );
+    }
+
+    #[test]
+    fn test_write_sample_data() {
+        let input = "sample,site\nsecond,gut\nfirst,skin\nthird,oral\n";
+        let samples = vec!["first".to_string(), "second".to_string()];
+
+        let mut output = Vec::new();
+        write_sample_data(input.as_bytes(), &mut output, &samples).unwrap();
+        pretty_assertions::assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "sample,site\nfirst,skin\nsecond,gut\n"
+        );
+
+        let missing = vec!["fourth".to_string()];
+        assert!(write_sample_data(input.as_bytes(), &mut Vec::new(), &missing).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use color_eyre::Report;
+use daggy::NodeIndex;
+use eyre::ContextCompat;
+use libspideog::{
+    data::{
+        abundance::SampleName,
+        tree::{Reads, Tree},
+    },
+    taxonomy::Rank,
+};
+use std::{collections::HashMap, io};
+
+use super::xml::escape;
+
+/// Rank of a taxon in the vocabulary of phyloXML
+fn phyloxml_rank(rank: Rank) -> &'static str {
+    match rank {
+        Rank::Domain(0) => "domain",
+        Rank::Kingdom(0) => "kingdom",
+        Rank::Phylum(0) => "phylum",
+        Rank::Class(0) => "class",
+        Rank::Order(0) => "order",
+        Rank::Family(0) => "family",
+        Rank::Genus(0) => "genus",
+        Rank::Species(0) => "species",
+        Rank::Unclassified(_) | Rank::Root(0) => "unknown",
+        _ => "other",
+    }
+}
+
+/// Property reference of a sample, phyloXML only allows letters, digits and underscores
+fn property_ref(sample: &str, property: &str) -> String {
+    let sample: String = sample
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    format!("{}:{}", sample, property)
+}
+
+/// Reads of the taxa of each sample
+struct SampleReads<'a> {
+    name: &'a str,
+    reads: HashMap<u64, Reads>,
+}
+
+impl<'a> SampleReads<'a> {
+    fn new(name: &'a str, tree: &Tree) -> Self {
+        Self {
+            name,
+            reads: tree.reads_by_taxonomy_id(),
+        }
+    }
+}
+
+/// Write a tree in the phyloXML format, each clade has its taxonomy and the reads of each sample as properties
+pub fn write_phyloxml<W: io::Write>(
+    writer: &mut W,
+    tree: &Tree,
+    samples: &[(SampleName, Tree)],
+) -> Result<(), Report> {
+    let origin = tree.origin.wrap_err("tree not initialized")?;
+    let samples: Vec<SampleReads> = samples
+        .iter()
+        .map(|(name, tree)| SampleReads::new(name, tree))
+        .collect();
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<phyloxml xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.phyloxml.org http://www.phyloxml.org/1.10/phyloxml.xsd" xmlns="http://www.phyloxml.org">"#
+    )?;
+    writeln!(writer, r#"  <phylogeny rooted="true">"#)?;
+    write_clade(writer, tree, origin, None, &samples, 2)?;
+    writeln!(writer, "  </phylogeny>")?;
+    writeln!(writer, "</phyloxml>")?;
+
+    Ok(())
+}
+
+fn write_clade<W: io::Write>(
+    writer: &mut W,
+    tree: &Tree,
+    index: NodeIndex,
+    parent_indent: Option<usize>,
+    samples: &[SampleReads],
+    depth: usize,
+) -> Result<(), Report> {
+    let node = tree.tree.node_weight(index).wrap_err("node not found")?;
+    let indent = "  ".repeat(depth);
+    let taxonomy_id = node.taxon.taxonomy_id;
+
+    writeln!(writer, "{}<clade>", indent)?;
+    writeln!(
+        writer,
+        "{}  <name>{}</name>",
+        indent,
+        escape(&node.taxon.name)
+    )?;
+    if let Some(parent_indent) = parent_indent {
+        let distance = node.indent.checked_sub(parent_indent).wrap_err_with(|| {
+            format!(
+                "failed to compute new distance: node {} - parent {}",
+                node.indent, parent_indent
+            )
+        })?;
+        writeln!(
+            writer,
+            "{}  <branch_length>{}</branch_length>",
+            indent, distance
+        )?;
+    }
+    writeln!(writer, "{}  <taxonomy>", indent)?;
+    writeln!(
+        writer,
+        r#"{}    <id provider="ncbi_taxonomy">{}</id>"#,
+        indent, taxonomy_id
+    )?;
+    writeln!(
+        writer,
+        "{}    <scientific_name>{}</scientific_name>",
+        indent,
+        escape(&node.taxon.name)
+    )?;
+    writeln!(
+        writer,
+        "{}    <rank>{}</rank>",
+        indent,
+        phyloxml_rank(node.taxon.taxonomy_level)
+    )?;
+    writeln!(writer, "{}  </taxonomy>", indent)?;
+
+    for sample in samples {
+        let reads = sample.reads.get(&taxonomy_id).copied().unwrap_or_default();
+        for (property, value) in &[
+            ("clade_count_reads", reads.clade),
+            ("taxon_count_reads", reads.taxon),
+        ] {
+            writeln!(
+                writer,
+                r#"{}  <property ref="{}" datatype="xsd:integer" applies_to="clade">{}</property>"#,
+                indent,
+                property_ref(sample.name, property),
+                value
+            )?;
+        }
+    }
+
+    for child in tree.children_of(index) {
+        write_clade(writer, tree, child, Some(node.indent), samples, depth + 1)?;
+    }
+
+    writeln!(writer, "{}</clade>", indent)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::fixtures::node;
+    use test_case::test_case;
+
+    #[test_case(Rank::Domain(0) => "domain")]
+    #[test_case(Rank::Species(0) => "species")]
+    #[test_case(Rank::Species(1) => "other")]
+    #[test_case(Rank::Root(0) => "unknown")]
+    fn test_phyloxml_rank(rank: Rank) -> &'static str {
+        phyloxml_rank(rank)
+    }
+
+    #[test_case("sample_2" => "sample_2:clade_count_reads")]
+    #[test_case("my sample.v1" => "my_sample_v1:clade_count_reads")]
+    fn test_property_ref(sample: &str) -> String {
+        property_ref(sample, "clade_count_reads")
+    }
+
+    #[test]
+    fn test_write_phyloxml() {
+        let mut tree = Tree::new();
+        tree.with_origin(node(1, "root", Rank::Root(0), 0, Some((10, 1))));
+        tree.child(
+            NodeIndex::new(0),
+            node(
+                562,
+                "Escherichia coli <K-12>",
+                Rank::Species(0),
+                3,
+                Some((4, 1)),
+            ),
+        );
+
+        let mut output = Vec::new();
+        write_phyloxml(&mut output, &tree, &[("sample".to_string(), tree.clone())]).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains(
+            "        <name>Escherichia coli &lt;K-12&gt;</name>\n        <branch_length>3</branch_length>\n        <taxonomy>\n          <id provider=\"ncbi_taxonomy\">562</id>"
+        ));
+        assert!(output.contains("<rank>species</rank>"));
+        assert!(output.contains(
+            r#"<property ref="sample:clade_count_reads" datatype="xsd:integer" applies_to="clade">4</property>"#
+        ));
+        pretty_assertions::assert_eq!(output.matches("<clade>").count(), 2);
+    }
+}
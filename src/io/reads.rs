@@ -0,0 +1,51 @@
+use std::{convert::TryFrom, io::Read};
+
+use csv::Reader;
+use libspideog::{
+    errors::SpideogError,
+    kraken::{
+        reads::{ClassifiedRead, ReadCounts},
+        KrakenRecord,
+    },
+    taxonomy::index::TaxonomyIndex,
+};
+use tracing::instrument;
+
+use super::report::FromKrakenRecords;
+
+/// Build a dataset from the Kraken2 per-read classification output (`--output`) and a taxonomy
+pub trait ParseKrakenReads: Sized {
+    fn parse_reads<R: Read>(
+        reader: &mut Reader<R>,
+        taxonomy: &TaxonomyIndex,
+    ) -> Result<Self, SpideogError>;
+}
+
+#[instrument(skip(reader))]
+pub fn count_reads<R: Read>(reader: &mut Reader<R>) -> Result<ReadCounts, SpideogError> {
+    let mut counts = ReadCounts::new();
+
+    for (line_number, result) in reader.records().enumerate() {
+        let record = result.map_err(SpideogError::KrakenParser)?;
+        let read = ClassifiedRead::try_from((line_number + 1, &record))?;
+        counts.add(&read);
+    }
+
+    Ok(counts)
+}
+
+impl<T: FromKrakenRecords> ParseKrakenReads for T {
+    #[instrument(skip(reader, taxonomy))]
+    fn parse_reads<R: Read>(
+        reader: &mut Reader<R>,
+        taxonomy: &TaxonomyIndex,
+    ) -> Result<Self, SpideogError> {
+        let report = count_reads(reader)?.to_report(taxonomy)?;
+
+        Self::from_records(
+            report
+                .into_iter()
+                .map(|record| Ok(KrakenRecord::Standard(record))),
+        )
+    }
+}
@@ -1,38 +1,61 @@
-use std::{convert::TryFrom, fs::File};
+use std::{
+    convert::TryFrom,
+    io::{self, Read},
+};
 
-use csv::Reader;
+use csv::{Reader, StringRecord};
 use libspideog::{
-    data::abundance::AbundanceData,
+    data::abundance::{AbundanceData, Lineage},
     data::tree::{IndentedTaxon, Tree},
     errors::SpideogError,
-    kraken::{Fragments, ReportRecord, Taxon},
+    kraken::{Fragments, KrakenRecord, ReportRecord},
 };
 use tracing::instrument;
 
 pub trait ParseKrakenReport: Sized {
-    fn parse(reader: &mut Reader<File>) -> Result<Self, SpideogError>;
+    fn parse<R: Read>(reader: &mut Reader<R>) -> Result<Self, SpideogError>;
+}
+
+/// Build a dataset from the lines of a Kraken report, read from a file or rebuilt from per-read classifications
+pub trait FromKrakenRecords: Sized {
+    fn from_records<I>(records: I) -> Result<Self, SpideogError>
+    where
+        I: IntoIterator<Item = Result<KrakenRecord, SpideogError>>;
+}
+
+impl<T: FromKrakenRecords> ParseKrakenReport for T {
+    #[instrument(skip(reader))]
+    fn parse<R: Read>(reader: &mut Reader<R>) -> Result<Self, SpideogError> {
+        Self::from_records(reader.records().map(parse_record))
+    }
+}
+
+fn parse_record(line: Result<StringRecord, csv::Error>) -> Result<KrakenRecord, SpideogError> {
+    let line = line.map_err(SpideogError::KrakenParser)?;
+    KrakenRecord::from_string_record(&line)
 }
 
 fn parse_origin_tree(
-    first_line: Option<Result<ReportRecord, csv::Error>>,
+    first_record: Option<Result<KrakenRecord, SpideogError>>,
 ) -> Result<Tree, SpideogError> {
-    let first_line = first_line.ok_or(SpideogError::EmptyFile)?;
-    let first_record: ReportRecord = first_line.map_err(SpideogError::KrakenParser)?;
+    let first_record = first_record.ok_or(SpideogError::EmptyFile)??;
     let origin = IndentedTaxon::try_from(first_record)?;
     let mut taxonomy_tree = Tree::new();
     taxonomy_tree.with_origin(origin);
     Ok(taxonomy_tree)
 }
 
-impl ParseKrakenReport for Tree {
-    #[instrument]
-    fn parse(reader: &mut Reader<File>) -> Result<Self, SpideogError> {
-        let first_line = reader.deserialize().next();
+impl FromKrakenRecords for Tree {
+    fn from_records<I>(records: I) -> Result<Self, SpideogError>
+    where
+        I: IntoIterator<Item = Result<KrakenRecord, SpideogError>>,
+    {
+        let mut records = records.into_iter();
 
-        let mut taxonomy_tree = parse_origin_tree(first_line)?;
+        let mut taxonomy_tree = parse_origin_tree(records.next())?;
 
-        for result in reader.deserialize() {
-            let record: ReportRecord = result.map_err(SpideogError::KrakenParser)?;
+        for result in records {
+            let record = result?;
             let node = IndentedTaxon::try_from(record)?;
             let parent = taxonomy_tree.find_valid_parent_for(&node)?;
             taxonomy_tree.child(parent, node);
@@ -42,18 +65,137 @@ impl ParseKrakenReport for Tree {
     }
 }
 
-impl ParseKrakenReport for AbundanceData {
-    #[instrument]
-    fn parse(reader: &mut Reader<File>) -> Result<Self, SpideogError> {
+impl FromKrakenRecords for AbundanceData {
+    fn from_records<I>(records: I) -> Result<Self, SpideogError>
+    where
+        I: IntoIterator<Item = Result<KrakenRecord, SpideogError>>,
+    {
         let mut data = Self::new();
+        let mut ancestors: Vec<IndentedTaxon> = Vec::new();
 
-        for result in reader.deserialize() {
-            let record: ReportRecord = result.map_err(SpideogError::KrakenParser)?;
-            let taxon = Taxon::try_from(record.clone())?;
+        for result in records {
+            let record = result?;
+            let node = IndentedTaxon::try_from(record.clone())?;
             let fragments = Fragments::try_from(record)?;
-            data.insert(taxon, fragments);
+
+            // the unclassified line is not part of the tree
+            if node.taxon.taxonomy_id != 0 {
+                while ancestors
+                    .last()
+                    .is_some_and(|last| last.indent >= node.indent)
+                {
+                    ancestors.pop();
+                }
+                ancestors.push(node.clone());
+                data.lineages.insert(
+                    node.taxon.clone(),
+                    ancestors
+                        .iter()
+                        .map(|ancestor| ancestor.taxon.clone())
+                        .collect::<Lineage>(),
+                );
+            }
+
+            data.insert(node.taxon, fragments);
         }
 
         Ok(data)
     }
 }
+
+/// Both datasets from the same records, to read a report only once
+impl<A: FromKrakenRecords, B: FromKrakenRecords> FromKrakenRecords for (A, B) {
+    fn from_records<I>(records: I) -> Result<Self, SpideogError>
+    where
+        I: IntoIterator<Item = Result<KrakenRecord, SpideogError>>,
+    {
+        let records = records.into_iter().collect::<Result<Vec<_>, _>>()?;
+
+        Ok((
+            A::from_records(records.iter().cloned().map(Ok))?,
+            B::from_records(records.into_iter().map(Ok))?,
+        ))
+    }
+}
+
+/// Write the lines of a Kraken report, without headers
+pub fn write_report<W: io::Write>(writer: &mut W, records: &[ReportRecord]) -> io::Result<()> {
+    for (percentage, clade, taxon, rank, taxonomy_id, name) in records {
+        let offset = match rank.offset() {
+            0 => String::new(),
+            offset => offset.to_string(),
+        };
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}{}\t{}\t{}",
+            percentage,
+            clade,
+            taxon,
+            rank.letter(),
+            offset,
+            taxonomy_id,
+            name
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libspideog::taxonomy::Rank;
+
+    fn records() -> Vec<ReportRecord> {
+        vec![
+            (
+                "25.00".to_string(),
+                1,
+                1,
+                Rank::Unclassified(0),
+                0,
+                "unclassified".to_string(),
+            ),
+            (
+                "75.00".to_string(),
+                3,
+                1,
+                Rank::Root(0),
+                1,
+                "root".to_string(),
+            ),
+            (
+                "50.00".to_string(),
+                2,
+                2,
+                Rank::Root(1),
+                131_567,
+                "  cellular organisms".to_string(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_write_report() {
+        let mut output = Vec::new();
+        write_report(&mut output, &records()).unwrap();
+
+        pretty_assertions::assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "25.00\t1\t1\tU\t0\tunclassified\n75.00\t3\t1\tR\t1\troot\n50.00\t2\t2\tR1\t131567\t  cellular organisms\n"
+        );
+    }
+
+    #[test]
+    fn test_tree_and_abundance_data_from_records() {
+        let (tree, data): (Tree, AbundanceData) = FromKrakenRecords::from_records(
+            records()
+                .into_iter()
+                .map(|record| Ok(KrakenRecord::Standard(record))),
+        )
+        .unwrap();
+
+        pretty_assertions::assert_eq!(tree.tree.node_count(), 3);
+        pretty_assertions::assert_eq!(data.len(), 3);
+    }
+}
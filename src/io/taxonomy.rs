@@ -0,0 +1,84 @@
+use color_eyre::{Help, Report};
+use eyre::Context;
+use libspideog::{
+    data::{
+        abundance::{resolve_taxa, AbundanceData},
+        tree::Tree,
+    },
+    errors::SpideogError,
+    taxonomy::index::TaxonomyIndex,
+};
+use std::path::PathBuf;
+use tracing::instrument;
+
+use super::open_file;
+
+/// Load a taxonomy from a NCBI taxdump directory (`nodes.dmp`, `names.dmp`, and optionally `merged.dmp`), a Kraken2 database directory, or a `ktaxonomy.tsv` file
+#[instrument]
+pub fn load_taxonomy(path: &PathBuf) -> Result<TaxonomyIndex, Report> {
+    let taxonomy = if path.is_dir() {
+        let nodes = path.join("nodes.dmp");
+        let names = path.join("names.dmp");
+        let ktaxonomy = path.join("ktaxonomy.tsv");
+
+        if nodes.exists() {
+            let merged = path.join("merged.dmp");
+            let merged = if merged.exists() {
+                Some(open_file(&merged)?)
+            } else {
+                None
+            };
+
+            TaxonomyIndex::from_taxdump(open_file(&nodes)?, open_file(&names)?).and_then(
+                |mut taxonomy| {
+                    if let Some(merged) = merged {
+                        taxonomy.read_merged(merged)?;
+                    }
+                    Ok(taxonomy)
+                },
+            )
+        } else if ktaxonomy.exists() {
+            TaxonomyIndex::from_ktaxonomy(open_file(&ktaxonomy)?)
+        } else {
+            return Err(eyre!("no taxonomy found in directory `{}`", path.display())).suggestion(
+                "the directory must contain `nodes.dmp` and `names.dmp`, or `ktaxonomy.tsv`",
+            );
+        }
+    } else {
+        TaxonomyIndex::from_ktaxonomy(open_file(path)?)
+    };
+
+    taxonomy.wrap_err_with(|| format!("failed to load taxonomy `{}`", path.display()))
+}
+
+/// Load the taxonomy needed to rebuild reports from per-read classifications
+pub fn require_taxonomy(path: &Option<PathBuf>) -> Result<TaxonomyIndex, Report> {
+    match path {
+        Some(path) => load_taxonomy(path),
+        None => Err(eyre!("a taxonomy is required to read Kraken per-read classifications"))
+            .suggestion("use the `--taxonomy` option with a NCBI taxdump directory, a Kraken2 database directory, or a `ktaxonomy.tsv` file"),
+    }
+}
+
+/// Datasets that can be checked and completed with a taxonomy
+pub trait Enrich: Sized {
+    fn enrich(self, taxonomy: &TaxonomyIndex) -> Result<Self, SpideogError>;
+}
+
+impl Enrich for Tree {
+    fn enrich(self, taxonomy: &TaxonomyIndex) -> Result<Self, SpideogError> {
+        self.with_taxonomy(taxonomy)
+    }
+}
+
+impl<V> Enrich for AbundanceData<V> {
+    fn enrich(self, taxonomy: &TaxonomyIndex) -> Result<Self, SpideogError> {
+        resolve_taxa(self, taxonomy)
+    }
+}
+
+impl<A: Enrich, B: Enrich> Enrich for (A, B) {
+    fn enrich(self, taxonomy: &TaxonomyIndex) -> Result<Self, SpideogError> {
+        Ok((self.0.enrich(taxonomy)?, self.1.enrich(taxonomy)?))
+    }
+}
@@ -0,0 +1,35 @@
+use std::borrow::Cow;
+
+/// Escape the characters with a special meaning in XML text and attribute values
+pub fn escape(input: &str) -> Cow<'_, str> {
+    if !input.contains(['&', '<', '>', '"', '\'']) {
+        return Cow::Borrowed(input);
+    }
+
+    let mut output = String::with_capacity(input.len() + 8);
+    for c in input.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&apos;"),
+            _ => output.push(c),
+        }
+    }
+
+    Cow::Owned(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("Escherichia coli" => "Escherichia coli")]
+    #[test_case("Bacillus sp. 'A&B' <1>" => "Bacillus sp. &apos;A&amp;B&apos; &lt;1&gt;")]
+    #[test_case("\"Candidatus\"" => "&quot;Candidatus&quot;")]
+    fn test_escape(input: &str) -> String {
+        escape(input).into_owned()
+    }
+}
@@ -1,12 +1,79 @@
+pub mod krakenuniq;
+pub mod reads;
+
 use std::{convert::TryFrom, fmt::Display};
 
 use tracing::instrument;
 
-use crate::{errors::SpideogError, parser::parse_ident_organism_name, taxonomy::Rank};
+use csv::StringRecord;
+
+use crate::{
+    data::abundance::ReadCounts, errors::SpideogError, parser::parse_ident_organism_name,
+    taxonomy::Rank,
+};
 
 pub type ReportRecord = (String, u64, u64, Rank, u64, String);
+/// Kraken2 report line produced with `--report-minimizer-data`
+pub type MinimizerReportRecord = (String, u64, u64, u64, u64, Rank, u64, String);
 pub type Indent = usize;
 
+/// Kraken report line, the layout is detected from the number of columns
+#[derive(Clone, PartialEq, Debug)]
+pub enum KrakenRecord {
+    Standard(ReportRecord),
+    Minimizer(MinimizerReportRecord),
+    /// KrakenUniq report line, with the rank already resolved
+    KrakenUniq(ReportRecord, KmerStats),
+}
+
+/// Values of a report line beyond the columns common to all report layouts
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct ExtraColumns {
+    pub minimizers: Option<Minimizers>,
+    pub kmers: Option<KmerStats>,
+}
+
+impl KrakenRecord {
+    pub fn from_string_record(record: &StringRecord) -> Result<Self, SpideogError> {
+        match record.len() {
+            6 => record
+                .deserialize(None)
+                .map(Self::Standard)
+                .map_err(SpideogError::KrakenParser),
+            8 => record
+                .deserialize(None)
+                .map(Self::Minimizer)
+                .map_err(SpideogError::KrakenParser),
+            n => Err(SpideogError::KrakenColumnCount(n)),
+        }
+    }
+
+    /// Split the layout specific columns from the common columns
+    #[must_use]
+    pub fn split(self) -> (ReportRecord, ExtraColumns) {
+        match self {
+            Self::Standard(record) => (record, ExtraColumns::default()),
+            Self::Minimizer(record) => (
+                (record.0, record.1, record.2, record.5, record.6, record.7),
+                ExtraColumns {
+                    minimizers: Some(Minimizers {
+                        minimizers: record.3,
+                        distinct_minimizers: record.4,
+                    }),
+                    kmers: None,
+                },
+            ),
+            Self::KrakenUniq(record, kmers) => (
+                record,
+                ExtraColumns {
+                    minimizers: None,
+                    kmers: Some(kmers),
+                },
+            ),
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, PartialOrd, Debug, Ord, Eq, Hash, Deserialize)]
 pub struct Taxon {
     #[serde(rename = "taxonomy_lvl")]
@@ -15,6 +82,17 @@ pub struct Taxon {
     pub taxonomy_id: u64,
 }
 
+impl Taxon {
+    #[must_use]
+    pub fn new(taxonomy_id: u64, name: &str, taxonomy_level: Rank) -> Self {
+        Self {
+            taxonomy_level,
+            name: name.to_string(),
+            taxonomy_id,
+        }
+    }
+}
+
 impl Display for Taxon {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -43,11 +121,48 @@ impl TryFrom<ReportRecord> for Taxon {
     }
 }
 
+impl TryFrom<KrakenRecord> for Taxon {
+    type Error = SpideogError;
+
+    fn try_from(value: KrakenRecord) -> Result<Self, Self::Error> {
+        Self::try_from(value.split().0)
+    }
+}
+
+/// Number of minimizers (and distinct minimizers) in the read sequences covered by the clade
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug, Serialize, Deserialize, Default)]
+pub struct Minimizers {
+    pub minimizers: u64,
+    pub distinct_minimizers: u64,
+}
+
+/// Unique k-mers count, k-mer duplication and coverage of the clade in the database (KrakenUniq)
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug, Serialize, Deserialize, Default)]
+pub struct KmerStats {
+    pub unique_kmers: u64,
+    pub duplication: f64,
+    pub coverage: Option<f64>,
+}
+
 #[derive(Clone, PartialEq, PartialOrd, Debug, Serialize, Deserialize, Default)]
 pub struct Fragments {
     pub clade_percentage: f64,
     pub clade_count_reads: u64,
     pub taxon_count_reads: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimizers: Option<Minimizers>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kmers: Option<KmerStats>,
+}
+
+impl ReadCounts for Fragments {
+    fn clade_reads(&self) -> u64 {
+        self.clade_count_reads
+    }
+
+    fn taxon_reads(&self) -> u64 {
+        self.taxon_count_reads
+    }
 }
 
 impl TryFrom<ReportRecord> for Fragments {
@@ -60,9 +175,100 @@ impl TryFrom<ReportRecord> for Fragments {
         let fragments = Self {
             clade_percentage: percentage,
             clade_count_reads: value.1,
-            taxon_count_reads: value.1,
+            taxon_count_reads: value.2,
+            minimizers: None,
+            kmers: None,
         };
 
         Ok(fragments)
     }
 }
+
+impl TryFrom<KrakenRecord> for Fragments {
+    type Error = SpideogError;
+
+    fn try_from(value: KrakenRecord) -> Result<Self, Self::Error> {
+        let (record, extra) = value.split();
+
+        Ok(Self {
+            minimizers: extra.minimizers,
+            kmers: extra.kmers,
+            ..Self::try_from(record)?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_taxon_count_reads_is_not_the_clade_count() {
+        let record: ReportRecord = (
+            "66.36".to_string(),
+            355,
+            12,
+            Rank::Root(0),
+            1,
+            "root".to_string(),
+        );
+
+        let fragments = Fragments::try_from(record).unwrap();
+
+        pretty_assertions::assert_eq!(fragments.clade_count_reads, 355);
+        pretty_assertions::assert_eq!(fragments.taxon_count_reads, 12);
+    }
+
+    #[test]
+    fn test_minimizer_record() {
+        let record = StringRecord::from(vec![
+            "9.35",
+            "50",
+            "50",
+            "1200",
+            "830",
+            "S",
+            "9606",
+            "                      Homo sapiens",
+        ]);
+
+        let record = KrakenRecord::from_string_record(&record).unwrap();
+        let fragments = Fragments::try_from(record.clone()).unwrap();
+        let taxon = Taxon::try_from(record).unwrap();
+
+        pretty_assertions::assert_eq!(
+            fragments,
+            Fragments {
+                clade_percentage: 9.35,
+                clade_count_reads: 50,
+                taxon_count_reads: 50,
+                minimizers: Some(Minimizers {
+                    minimizers: 1200,
+                    distinct_minimizers: 830
+                }),
+                kmers: None,
+            }
+        );
+        pretty_assertions::assert_eq!(taxon.name, "Homo sapiens");
+        pretty_assertions::assert_eq!(taxon.taxonomy_id, 9606);
+    }
+
+    #[test]
+    fn test_standard_record() {
+        let record = StringRecord::from(vec!["66.36", "355", "12", "R", "1", "root"]);
+
+        let fragments =
+            Fragments::try_from(KrakenRecord::from_string_record(&record).unwrap()).unwrap();
+
+        pretty_assertions::assert_eq!(fragments.clade_count_reads, 355);
+        pretty_assertions::assert_eq!(fragments.taxon_count_reads, 12);
+        pretty_assertions::assert_eq!(fragments.minimizers, None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_column_count() {
+        let record = StringRecord::from(vec!["66.36", "355", "0", "R"]);
+        KrakenRecord::from_string_record(&record).unwrap();
+    }
+}
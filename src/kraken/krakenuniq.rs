@@ -0,0 +1,145 @@
+use crate::{
+    errors::SpideogError,
+    kraken::{Indent, KmerStats, KrakenRecord},
+    parser::parse_ident_organism_name,
+    taxonomy::{canonical_rank, index::ROOT_TAXONOMY_ID, Rank},
+};
+
+/// KrakenUniq report line: %, reads, taxReads, kmers, dup, cov, taxID, rank, taxName
+pub type KrakenUniqReportRecord = (String, u64, u64, u64, f64, String, u64, String, String);
+
+/// Resolve the full word ranks of KrakenUniq (`species`, `no rank`, ...) into Kraken rank codes
+///
+/// Non canonical ranks are offsets of the parent rank (e.g. a `subspecies` is `S1`), the parent is found with the indentation of the taxon names.
+#[derive(Debug, Default)]
+pub struct KrakenUniqRanks {
+    ancestors: Vec<(Indent, Rank)>,
+}
+
+impl KrakenUniqRanks {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            ancestors: Vec::new(),
+        }
+    }
+
+    fn resolve_rank(&mut self, indent: Indent, taxonomy_id: u64, ncbi_rank: &str) -> Rank {
+        while self
+            .ancestors
+            .last()
+            .is_some_and(|(ancestor_indent, _)| *ancestor_indent >= indent)
+        {
+            self.ancestors.pop();
+        }
+
+        let rank = if taxonomy_id == 0 {
+            Rank::Unclassified(0)
+        } else if taxonomy_id == ROOT_TAXONOMY_ID {
+            Rank::Root(0)
+        } else if let Some(rank) = canonical_rank(ncbi_rank) {
+            rank
+        } else {
+            self.ancestors
+                .last()
+                .map_or(Rank::Root(0), |(_, parent_rank)| parent_rank.plus_one())
+        };
+
+        self.ancestors.push((indent, rank));
+
+        rank
+    }
+
+    pub fn resolve(
+        &mut self,
+        record: KrakenUniqReportRecord,
+    ) -> Result<KrakenRecord, SpideogError> {
+        let (
+            percentage,
+            reads,
+            taxon_reads,
+            unique_kmers,
+            duplication,
+            coverage,
+            taxonomy_id,
+            ncbi_rank,
+            name,
+        ) = record;
+
+        let (_, (indent, _)) = parse_ident_organism_name(name.as_bytes())
+            .map_err(|_e| SpideogError::KrakenIndentParsing)?;
+
+        let rank = self.resolve_rank(indent, taxonomy_id, ncbi_rank.trim());
+
+        let kmers = KmerStats {
+            unique_kmers,
+            duplication,
+            coverage: coverage.trim().parse::<f64>().ok(),
+        };
+
+        Ok(KrakenRecord::KrakenUniq(
+            (percentage, reads, taxon_reads, rank, taxonomy_id, name),
+            kmers,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kraken::Fragments;
+    use std::convert::TryFrom;
+
+    static REPORT: &str = "# KrakenUniq v0.5.8 DATE:2020-11-20T10:00:00Z
+# Database(s): /data/krakenuniq/db
+%\treads\ttaxReads\tkmers\tdup\tcov\ttaxID\trank\ttaxName
+33.64\t180\t180\t0\t0\tNA\t0\tno rank\tunclassified
+66.36\t355\t0\t12000\t1.2\t0.0001\t1\tno rank\troot
+66.36\t355\t0\t12000\t1.2\t0.0001\t131567\tno rank\t  cellular organisms
+20.19\t108\t0\t8000\t1.1\t0.001\t2\tsuperkingdom\t    Bacteria
+5.61\t30\t0\t3000\t1.1\t0.01\t562\tspecies\t      Escherichia coli
+1.12\t6\t6\t600\t1.0\t0.2\t83333\tsubspecies\t        Escherichia coli K-12
+";
+
+    #[test]
+    fn test_resolve() {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .comment(Some(b'#'))
+            .delimiter(b'\t')
+            .from_reader(REPORT.as_bytes());
+
+        let mut ranks = KrakenUniqRanks::new();
+        let records: Vec<KrakenRecord> = reader
+            .deserialize()
+            .map(|r: Result<KrakenUniqReportRecord, csv::Error>| ranks.resolve(r.unwrap()).unwrap())
+            .collect();
+
+        let resolved_ranks: Vec<Rank> = records
+            .iter()
+            .map(|record| record.clone().split().0 .3)
+            .collect();
+
+        pretty_assertions::assert_eq!(
+            resolved_ranks,
+            vec![
+                Rank::Unclassified(0),
+                Rank::Root(0),
+                Rank::Root(1),
+                Rank::Domain(0),
+                Rank::Species(0),
+                Rank::Species(1)
+            ]
+        );
+
+        let fragments = Fragments::try_from(records[0].clone()).unwrap();
+        pretty_assertions::assert_eq!(
+            fragments.kmers,
+            Some(KmerStats {
+                unique_kmers: 0,
+                duplication: 0.0,
+                coverage: None
+            })
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use std::{collections::HashMap, convert::TryFrom};
+
+use csv::StringRecord;
+use tracing::warn;
+
+use crate::{
+    errors::SpideogError,
+    kraken::ReportRecord,
+    parser::{parse_classified_taxid, parse_read_length},
+    taxonomy::{
+        index::{TaxonomyIndex, ROOT_TAXONOMY_ID},
+        Rank,
+    },
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ClassificationStatus {
+    Classified,
+    Unclassified,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReadLength {
+    Single(u64),
+    Paired(u64, u64),
+}
+
+/// One line of the Kraken2 per-read output: status, read id, taxid, length, k-mer LCA mappings
+#[derive(Clone, PartialEq, Debug)]
+pub struct ClassifiedRead {
+    pub status: ClassificationStatus,
+    pub read_id: String,
+    pub taxonomy_id: u64,
+    pub length: ReadLength,
+}
+
+impl TryFrom<(usize, &StringRecord)> for ClassifiedRead {
+    type Error = SpideogError;
+
+    fn try_from((line_number, record): (usize, &StringRecord)) -> Result<Self, Self::Error> {
+        let error = || SpideogError::KrakenReadParsing(line_number);
+
+        let status = match record.get(0).map(str::trim) {
+            Some("C") => ClassificationStatus::Classified,
+            Some("U") => ClassificationStatus::Unclassified,
+            _ => return Err(error()),
+        };
+        let read_id = record.get(1).ok_or_else(error)?.to_string();
+        let (_, taxonomy_id) =
+            parse_classified_taxid(record.get(2).ok_or_else(error)?).map_err(|_e| error())?;
+        let (_, length) =
+            parse_read_length(record.get(3).ok_or_else(error)?).map_err(|_e| error())?;
+
+        let length = match length {
+            (length, None) => ReadLength::Single(length),
+            (length_1, Some(length_2)) => ReadLength::Paired(length_1, length_2),
+        };
+
+        Ok(Self {
+            status,
+            read_id,
+            taxonomy_id,
+            length,
+        })
+    }
+}
+
+/// Number of reads assigned directly to each taxon
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReadCounts {
+    pub taxon_reads: HashMap<u64, u64>,
+    pub unclassified_reads: u64,
+}
+
+impl ReadCounts {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            taxon_reads: HashMap::new(),
+            unclassified_reads: 0,
+        }
+    }
+
+    pub fn add(&mut self, read: &ClassifiedRead) -> &mut Self {
+        if read.status == ClassificationStatus::Unclassified || read.taxonomy_id == 0 {
+            self.unclassified_reads += 1;
+        } else {
+            *self.taxon_reads.entry(read.taxonomy_id).or_insert(0) += 1;
+        }
+
+        self
+    }
+
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.unclassified_reads + self.taxon_reads.values().sum::<u64>()
+    }
+
+    /// Rebuild the lines of a Kraken report: unclassified first, then the taxonomy from the root
+    /// in depth-first order with siblings sorted by decreasing clade size, as Kraken2 does.
+    ///
+    /// Merged taxids are counted with their current taxid. The taxids unknown to the taxonomy are
+    /// placed under the root, after its other children.
+    pub fn to_report(&self, taxonomy: &TaxonomyIndex) -> Result<Vec<ReportRecord>, SpideogError> {
+        let mut clade_reads: HashMap<u64, u64> = HashMap::new();
+        let mut taxon_reads: HashMap<u64, u64> = HashMap::new();
+        let mut unknown: Vec<(u64, u64)> = Vec::new();
+
+        for (taxonomy_id, reads) in &self.taxon_reads {
+            if let Some(current_id) = taxonomy.resolve_id(*taxonomy_id) {
+                *taxon_reads.entry(current_id).or_insert(0) += reads;
+                for ancestor in taxonomy.lineage(current_id)? {
+                    *clade_reads.entry(ancestor).or_insert(0) += reads;
+                }
+            } else {
+                warn!(
+                    "taxid `{}` is not in the taxonomy, it is placed under the root",
+                    taxonomy_id
+                );
+                *clade_reads.entry(ROOT_TAXONOMY_ID).or_insert(0) += reads;
+                unknown.push((*taxonomy_id, *reads));
+            }
+        }
+        unknown.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let mut children: HashMap<u64, Vec<u64>> = HashMap::new();
+        for taxonomy_id in clade_reads.keys() {
+            if let Some(parent) = taxonomy.parent(*taxonomy_id) {
+                children.entry(parent).or_default().push(*taxonomy_id);
+            }
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_by(|a, b| clade_reads[b].cmp(&clade_reads[a]).then(a.cmp(b)));
+        }
+
+        let total = self.total();
+        let percentage = |reads: u64| {
+            if total == 0 {
+                String::from("0.00")
+            } else {
+                format!("{:.2}", reads as f64 * 100.0 / total as f64)
+            }
+        };
+
+        let mut records = Vec::with_capacity(clade_reads.len() + 1);
+
+        if self.unclassified_reads > 0 {
+            records.push((
+                percentage(self.unclassified_reads),
+                self.unclassified_reads,
+                self.unclassified_reads,
+                Rank::Unclassified(0),
+                0,
+                String::from("unclassified"),
+            ));
+        }
+
+        let mut roots: Vec<u64> = clade_reads
+            .keys()
+            .filter(|taxonomy_id| taxonomy.parent(**taxonomy_id).is_none())
+            .copied()
+            .collect();
+        roots.sort_by_key(|taxonomy_id| (*taxonomy_id != ROOT_TAXONOMY_ID, *taxonomy_id));
+
+        let mut stack: Vec<(u64, usize)> = roots.into_iter().rev().map(|r| (r, 0)).collect();
+
+        while let Some((taxonomy_id, depth)) = stack.pop() {
+            let (taxonomy_level, name, clade, taxon) = match taxonomy.get(taxonomy_id) {
+                Some(node) => (
+                    node.taxonomy_level,
+                    node.name.clone(),
+                    clade_reads[&taxonomy_id],
+                    taxon_reads.get(&taxonomy_id).copied().unwrap_or(0),
+                ),
+                None => {
+                    let reads = self.taxon_reads.get(&taxonomy_id).copied().unwrap_or(0);
+                    (
+                        Rank::Root(1),
+                        format!("taxid {}", taxonomy_id),
+                        reads,
+                        reads,
+                    )
+                }
+            };
+
+            records.push((
+                percentage(clade),
+                clade,
+                taxon,
+                taxonomy_level,
+                taxonomy_id,
+                format!("{}{}", "  ".repeat(depth), name),
+            ));
+
+            if taxonomy_id == ROOT_TAXONOMY_ID {
+                stack.extend(unknown.iter().rev().map(|(child, _)| (*child, depth + 1)));
+            }
+            if let Some(siblings) = children.get(&taxonomy_id) {
+                stack.extend(siblings.iter().rev().map(|child| (*child, depth + 1)));
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::taxonomy::index::tests::small_taxonomy;
+
+    fn read(line: &str) -> ClassifiedRead {
+        let record = StringRecord::from(line.split('\t').collect::<Vec<&str>>());
+        ClassifiedRead::try_from((1, &record)).unwrap()
+    }
+
+    #[test]
+    fn test_classified_read() {
+        pretty_assertions::assert_eq!(
+            read("C\tread_1\tEscherichia coli (taxid 562)\t150|148\t562:116 |:| 562:114"),
+            ClassifiedRead {
+                status: ClassificationStatus::Classified,
+                read_id: "read_1".to_string(),
+                taxonomy_id: 562,
+                length: ReadLength::Paired(150, 148),
+            }
+        );
+    }
+
+    #[test]
+    fn test_to_report() {
+        let mut counts = ReadCounts::new();
+        for line in &[
+            "U\tread_1\t0\t150\t0:116",
+            "C\tread_2\t562\t150\t562:116",
+            "C\tread_3\t562\t150\t562:116",
+            "C\tread_4\t1224\t150\t1224:116",
+            "C\tread_5\t1239\t150\t1239:116",
+        ] {
+            counts.add(&read(line));
+        }
+
+        let report = counts.to_report(&small_taxonomy()).unwrap();
+
+        pretty_assertions::assert_eq!(
+            report,
+            vec![
+                (
+                    "20.00".to_string(),
+                    1,
+                    1,
+                    Rank::Unclassified(0),
+                    0,
+                    "unclassified".to_string()
+                ),
+                (
+                    "80.00".to_string(),
+                    4,
+                    0,
+                    Rank::Root(0),
+                    1,
+                    "root".to_string()
+                ),
+                (
+                    "80.00".to_string(),
+                    4,
+                    0,
+                    Rank::Root(1),
+                    131_567,
+                    "  cellular organisms".to_string()
+                ),
+                (
+                    "80.00".to_string(),
+                    4,
+                    0,
+                    Rank::Domain(0),
+                    2,
+                    "    Bacteria".to_string()
+                ),
+                (
+                    "60.00".to_string(),
+                    3,
+                    1,
+                    Rank::Phylum(0),
+                    1224,
+                    "      Proteobacteria".to_string()
+                ),
+                (
+                    "40.00".to_string(),
+                    2,
+                    2,
+                    Rank::Species(0),
+                    562,
+                    "        Escherichia coli".to_string()
+                ),
+                (
+                    "20.00".to_string(),
+                    1,
+                    1,
+                    Rank::Phylum(0),
+                    1239,
+                    "      Firmicutes".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_report_merged_taxid() {
+        let mut taxonomy = small_taxonomy();
+        taxonomy.insert_merged(83333, 562);
+
+        let mut counts = ReadCounts::new();
+        counts.add(&read("C\tread_1\t562\t150\t562:116"));
+        counts.add(&read("C\tread_2\t83333\t150\t83333:116"));
+
+        let report = counts.to_report(&taxonomy).unwrap();
+
+        pretty_assertions::assert_eq!(report.len(), 5);
+        pretty_assertions::assert_eq!(
+            report[4],
+            (
+                "100.00".to_string(),
+                2,
+                2,
+                Rank::Species(0),
+                562,
+                "        Escherichia coli".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_report_unknown_taxid() {
+        let mut counts = ReadCounts::new();
+        for line in &[
+            "C\tread_1\t562\t150\t562:116",
+            "C\tread_2\t9606\t150\t9606:116",
+            "C\tread_3\t9606\t150\t9606:116",
+        ] {
+            counts.add(&read(line));
+        }
+
+        let report = counts.to_report(&small_taxonomy()).unwrap();
+
+        pretty_assertions::assert_eq!(report[0].1, 3);
+        pretty_assertions::assert_eq!(report[0].4, 1);
+        pretty_assertions::assert_eq!(
+            report.last().unwrap(),
+            &(
+                "66.67".to_string(),
+                2,
+                2,
+                Rank::Root(1),
+                9606,
+                "  taxid 9606".to_string()
+            )
+        );
+    }
+}
@@ -6,8 +6,10 @@
 
 pub mod bracken;
 pub mod data;
+pub mod diversity;
 pub mod errors;
 pub mod kraken;
+pub mod metaphlan;
 pub mod parser;
 pub mod taxonomy;
 
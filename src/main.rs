@@ -38,6 +38,16 @@ pub enum BinError {
         err: std::io::Error,
         path: std::path::PathBuf,
     },
+    /// failed to parse file `{path}`
+    Parse {
+        #[source]
+        err: libspideog::errors::SpideogError,
+        path: std::path::PathBuf,
+    },
+    /// a taxonomy is required to read the Kraken per-read classifications `{0}`
+    MissingTaxonomy(std::path::PathBuf),
+    /// Bracken abundance table `{0}` does not contain a taxonomy tree
+    BrackenTree(std::path::PathBuf),
     /// encountered multiple errors
     MultipleErrors,
 }
@@ -63,6 +73,15 @@ fn main() -> Result<(), Report> {
         Command::CombineAbundances(args) => {
             args.run().wrap_err("failed to combine abundance data")?;
         }
+        Command::ExportPhyloseq(args) => {
+            args.run().wrap_err("failed to export phyloseq files")?;
+        }
+        Command::Diversity(args) => {
+            args.run().wrap_err("failed to compute diversity")?;
+        }
+        Command::Rarefy(args) => {
+            args.run().wrap_err("failed to rarefy reports")?;
+        }
     }
 
     Ok(())
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use csv::StringRecord;
+
+use crate::{
+    errors::SpideogError,
+    kraken::ReportRecord,
+    taxonomy::{index::ROOT_TAXONOMY_ID, Rank},
+};
+
+/// One clade of a MetaPhlAn profile, e.g. `k__Bacteria|p__Firmicutes  2|1239  60.5`
+#[derive(Clone, PartialEq, Debug)]
+pub struct MetaphlanRecord {
+    /// clade names from the kingdom, e.g. `["k__Bacteria", "p__Firmicutes"]`
+    pub clade: Vec<String>,
+    pub taxonomy_id: Option<u64>,
+    pub relative_abundance: f64,
+    pub estimated_reads: Option<u64>,
+}
+
+/// Kraken rank code of a MetaPhlAn clade prefix
+fn clade_rank(prefix: &str) -> Option<Rank> {
+    match prefix {
+        "k" | "d" => Some(Rank::Domain(0)),
+        "p" => Some(Rank::Phylum(0)),
+        "c" => Some(Rank::Class(0)),
+        "o" => Some(Rank::Order(0)),
+        "f" => Some(Rank::Family(0)),
+        "g" => Some(Rank::Genus(0)),
+        "s" => Some(Rank::Species(0)),
+        "t" => Some(Rank::Species(1)),
+        _ => None,
+    }
+}
+
+/// Rank and name of a clade, `s__Escherichia_coli` is `S` and `Escherichia coli`
+fn clade_rank_name(clade: &str) -> Result<(Rank, String), SpideogError> {
+    let mut parts = clade.splitn(2, "__");
+    let prefix = parts.next().unwrap_or_default();
+    let name = parts
+        .next()
+        .ok_or_else(|| SpideogError::MetaphlanCladeParsing(clade.to_string()))?;
+    let rank =
+        clade_rank(prefix).ok_or_else(|| SpideogError::MetaphlanCladeParsing(clade.to_string()))?;
+
+    Ok((rank, name.replace('_', " ")))
+}
+
+/// Estimated reads of a clade, only written by MetaPhlAn with `-t rel_ab_w_read_stats`
+fn estimated_reads(record: &MetaphlanRecord) -> Result<u64, SpideogError> {
+    record
+        .estimated_reads
+        .ok_or(SpideogError::MetaphlanWithoutReads)
+}
+
+fn is_unclassified(clade: &str) -> bool {
+    clade == "UNCLASSIFIED" || clade == "UNKNOWN" || clade == "unclassified"
+}
+
+impl MetaphlanRecord {
+    /// Columns are inferred from their number: `clade_name, relative_abundance` (MetaPhlAn 2),
+    /// `clade_name, NCBI_tax_id, relative_abundance[, additional_species]` (MetaPhlAn 3 and 4), or
+    /// `clade_name, clade_taxid, relative_abundance, coverage, estimated_number_of_reads_from_the_clade`
+    pub fn from_string_record(record: &StringRecord) -> Result<Self, SpideogError> {
+        let error =
+            || SpideogError::MetaphlanLineParsing(record.iter().collect::<Vec<_>>().join("\t"));
+
+        let clade_name = record.get(0).ok_or_else(error)?.trim();
+        let (taxonomy_ids, relative_abundance, estimated_reads) = match record.len() {
+            2 => (None, record.get(1), None),
+            3 | 4 => (record.get(1), record.get(2), None),
+            5 => (record.get(1), record.get(2), record.get(4)),
+            _ => return Err(error()),
+        };
+
+        let relative_abundance = relative_abundance
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .ok_or_else(error)?;
+        let taxonomy_id = taxonomy_ids
+            .and_then(|ids| ids.rsplit('|').next())
+            .and_then(|id| id.trim().parse::<u64>().ok());
+        let estimated_reads = estimated_reads.and_then(|reads| reads.trim().parse::<u64>().ok());
+
+        Ok(Self {
+            clade: clade_name.split('|').map(String::from).collect(),
+            taxonomy_id,
+            relative_abundance,
+            estimated_reads,
+        })
+    }
+}
+
+/// Rebuild the lines of a Kraken report from the clades of a MetaPhlAn profile
+///
+/// MetaPhlAn lists the clades level by level, the report lists them depth-first under a `root` node
+/// with siblings sorted by decreasing abundance. Taxon read counts are the clade read counts minus
+/// the read counts of the sub-clades.
+///
+/// The read counts are the estimated reads of the profiles written with `-t rel_ab_w_read_stats`,
+/// the profiles without them are rejected.
+pub fn to_report(records: &[MetaphlanRecord]) -> Result<Vec<ReportRecord>, SpideogError> {
+    let mut report = Vec::with_capacity(records.len() + 2);
+
+    let (unclassified, classified): (Vec<&MetaphlanRecord>, Vec<&MetaphlanRecord>) = records
+        .iter()
+        .partition(|record| record.clade.len() == 1 && is_unclassified(&record.clade[0]));
+
+    for record in unclassified {
+        let reads = estimated_reads(record)?;
+        report.push((
+            record.relative_abundance.to_string(),
+            reads,
+            reads,
+            Rank::Unclassified(0),
+            0,
+            String::from("unclassified"),
+        ));
+    }
+
+    let by_clade: HashMap<&[String], &MetaphlanRecord> = classified
+        .iter()
+        .map(|record| (record.clade.as_slice(), *record))
+        .collect();
+
+    let mut children: HashMap<&[String], Vec<&MetaphlanRecord>> = HashMap::new();
+    for record in &classified {
+        let parent = &record.clade[..record.clade.len() - 1];
+        let parent = if by_clade.contains_key(parent) {
+            parent
+        } else {
+            &record.clade[..0]
+        };
+        children.entry(parent).or_default().push(record);
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by(|a, b| {
+            b.relative_abundance
+                .partial_cmp(&a.relative_abundance)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.clade.cmp(&b.clade))
+        });
+    }
+
+    let sub_clades_reads = |clade: &[String]| -> Result<u64, SpideogError> {
+        children.get(clade).map_or(Ok(0), |siblings| {
+            siblings.iter().map(|record| estimated_reads(record)).sum()
+        })
+    };
+
+    let top_level: &[String] = &[];
+    let root_reads = sub_clades_reads(top_level)?;
+    let root_abundance: f64 = children.get(top_level).map_or(0.0, |siblings| {
+        siblings.iter().map(|r| r.relative_abundance).sum()
+    });
+
+    report.push((
+        root_abundance.to_string(),
+        root_reads,
+        0,
+        Rank::Root(0),
+        ROOT_TAXONOMY_ID,
+        String::from("root"),
+    ));
+
+    let mut stack: Vec<(&MetaphlanRecord, usize)> =
+        children.get(top_level).map_or_else(Vec::new, |siblings| {
+            siblings.iter().rev().map(|record| (*record, 1)).collect()
+        });
+
+    while let Some((record, depth)) = stack.pop() {
+        let (rank, name) = clade_rank_name(record.clade.last().unwrap())?;
+        let reads = estimated_reads(record)?;
+
+        report.push((
+            record.relative_abundance.to_string(),
+            reads,
+            reads.saturating_sub(sub_clades_reads(&record.clade)?),
+            rank,
+            record.taxonomy_id.unwrap_or(0),
+            format!("{}{}", "  ".repeat(depth), name),
+        ));
+
+        if let Some(siblings) = children.get(record.clade.as_slice()) {
+            stack.extend(siblings.iter().rev().map(|child| (*child, depth + 1)));
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(line: &str) -> MetaphlanRecord {
+        MetaphlanRecord::from_string_record(&StringRecord::from(
+            line.split('\t').collect::<Vec<&str>>(),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_record() {
+        pretty_assertions::assert_eq!(
+            record("k__Bacteria|p__Firmicutes\t2|1239\t60.5\t1200.5\t3400"),
+            MetaphlanRecord {
+                clade: vec!["k__Bacteria".to_string(), "p__Firmicutes".to_string()],
+                taxonomy_id: Some(1239),
+                relative_abundance: 60.5,
+                estimated_reads: Some(3400),
+            }
+        );
+    }
+
+    #[test]
+    fn test_to_report() {
+        let records = vec![
+            record("k__Bacteria\t2\t100.0\t12.0\t100"),
+            record("k__Bacteria|p__Proteobacteria\t2|1224\t40.0\t5.0\t40"),
+            record("k__Bacteria|p__Firmicutes\t2|1239\t60.0\t7.0\t60"),
+            record("k__Bacteria|p__Firmicutes|s__Lactococcus_lactis\t2|1239|1358\t60.0\t7.0\t55"),
+        ];
+
+        let report = to_report(&records).unwrap();
+
+        pretty_assertions::assert_eq!(
+            report.iter().map(|r| r.5.as_str()).collect::<Vec<&str>>(),
+            vec![
+                "root",
+                "  Bacteria",
+                "    Firmicutes",
+                "      Lactococcus lactis",
+                "    Proteobacteria"
+            ]
+        );
+        pretty_assertions::assert_eq!(report[3].3, Rank::Species(0));
+        pretty_assertions::assert_eq!(report[3].4, 1358);
+        pretty_assertions::assert_eq!(report[0].0, "100");
+        pretty_assertions::assert_eq!((report[0].1, report[0].2), (100, 0));
+        pretty_assertions::assert_eq!((report[2].1, report[2].2), (60, 5));
+    }
+
+    #[test]
+    fn test_to_report_without_reads() {
+        let records = vec![
+            record("k__Bacteria\t2\t100.0\t"),
+            record("k__Bacteria|p__Firmicutes\t2|1239\t100.0\t"),
+        ];
+
+        assert!(matches!(
+            to_report(&records),
+            Err(SpideogError::MetaphlanWithoutReads)
+        ));
+    }
+}
@@ -18,3 +18,52 @@ pub fn parse_ident_organism_name(input: &[u8]) -> IResult<&[u8], (Indent, &[u8])
 
     Ok((&[], (spaces.len(), name)))
 }
+
+fn parse_u64(input: &str) -> IResult<&str, u64> {
+    nom::combinator::map_res(nom::character::complete::digit1, str::parse::<u64>)(input)
+}
+
+/// taxonomy id of a read classification, either `9606` or `Homo sapiens (taxid 9606)` with `--use-names`
+pub fn parse_classified_taxid(input: &str) -> IResult<&str, u64> {
+    nom::branch::alt((
+        nom::combinator::all_consuming(parse_u64),
+        nom::sequence::delimited(
+            nom::sequence::pair(
+                nom::bytes::complete::take_until("(taxid "),
+                nom::bytes::complete::tag("(taxid "),
+            ),
+            parse_u64,
+            nom::bytes::complete::tag(")"),
+        ),
+    ))(input.trim())
+}
+
+/// sequence length of a read classification, `150` or `150|148` for paired-end reads
+pub fn parse_read_length(input: &str) -> IResult<&str, (u64, Option<u64>)> {
+    nom::combinator::all_consuming(nom::sequence::pair(
+        parse_u64,
+        nom::combinator::opt(nom::sequence::preceded(
+            nom::character::complete::char('|'),
+            parse_u64,
+        )),
+    ))(input.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("9606", 9606; "taxid")]
+    #[test_case("Homo sapiens (taxid 9606)", 9606; "with_names")]
+    #[test_case("unclassified (taxid 0)", 0; "unclassified")]
+    fn test_parse_classified_taxid(input: &str, expected: u64) {
+        pretty_assertions::assert_eq!(parse_classified_taxid(input).unwrap().1, expected);
+    }
+
+    #[test_case("150", (150, None); "single")]
+    #[test_case("150|148", (150, Some(148)); "paired")]
+    fn test_parse_read_length(input: &str, expected: (u64, Option<u64>)) {
+        pretty_assertions::assert_eq!(parse_read_length(input).unwrap().1, expected);
+    }
+}
@@ -2,3 +2,6 @@ mod combine_abundance;
 mod combine_phylo;
 mod convert_abundance;
 mod convert_phylo;
+mod diversity;
+mod export_phyloseq;
+mod rarefy;
@@ -1,84 +1,137 @@
 use color_eyre::{Help, Report};
 use eyre::Context;
 use libspideog::{
+    bracken::AbundanceValues,
     data::abundance::{AbundanceData, SampleName, Samples},
-    errors::SpideogError,
 };
+use serde::Serialize;
 use std::iter::FromIterator;
 use tracing::instrument;
 
 use crate::{
     cli::subcommands::{CombineAbundances, Runner},
-    io::{abundance_csv::WriteAbundanceCsv, report::ParseKrakenReport, Output},
+    io::{
+        abundance_csv::{WideValue, WriteAbundanceCsv, WriteWideCsv},
+        biom::WriteBiom,
+        check_lineage, join_errors,
+        json::{write_json, write_ndjson_samples},
+        mpa::MpaTable,
+        sample_name, AbundanceLayout, AbundanceValue, InputReportFormat, Output,
+        OutputAbundanceFormat, ReportInput,
+    },
+    BinError,
 };
 
-type VecResultAbundanceData = Vec<Result<(SampleName, AbundanceData), SpideogError>>;
+type VecResultAbundanceData<V> = Vec<Result<(SampleName, AbundanceData<V>), BinError>>;
 
-impl Runner for CombineAbundances {
-    #[instrument]
-    fn run(self) -> Result<(), Report> {
-        let sample_names: Vec<SampleName> = self
-            .input
-            .paths
-            .iter()
-            .map(|p| p.file_stem().unwrap().to_string_lossy().into())
-            .collect();
-        let readers = self.input.open_reports()?;
-        let output = Output::from(self.output.file.clone());
-        output.try_writtable()?;
+fn read_samples<V, F>(inputs: Vec<ReportInput>, parse: F) -> Result<Samples<V>, Report>
+where
+    F: Fn(&mut ReportInput) -> Result<AbundanceData<V>, BinError>,
+{
+    let (ok_abundance_data, errors_abundance_data): (
+        VecResultAbundanceData<V>,
+        VecResultAbundanceData<V>,
+    ) = inputs
+        .into_iter()
+        .map(
+            |mut input| -> Result<(SampleName, AbundanceData<V>), BinError> {
+                let data = parse(&mut input)?;
 
-        let (ok_abundance_data, errors_abundance_data): (
-            VecResultAbundanceData,
-            VecResultAbundanceData,
-        ) = readers
-            .into_iter()
-            .zip(sample_names)
-            .map(
-                |(file, sample_name)| -> Result<(SampleName, AbundanceData), SpideogError> {
-                    let mut csv_reader = csv::ReaderBuilder::new()
-                        .has_headers(self.input.headers)
-                        .delimiter(b'\t')
-                        .double_quote(false)
-                        .flexible(true)
-                        .from_reader(file);
+                Ok((sample_name(&input.path), data))
+            },
+        )
+        .partition(Result::is_ok);
 
-                    let tree: AbundanceData = ParseKrakenReport::parse(&mut csv_reader)?;
+    join_errors(errors_abundance_data)?;
 
-                    Ok((sample_name, tree))
-                },
-            )
-            .partition(Result::is_ok);
+    Ok(Samples::from_iter(
+        ok_abundance_data.into_iter().map(Result::unwrap),
+    ))
+}
 
-        if !errors_abundance_data.is_empty() {
-            return errors_abundance_data
-                .into_iter()
-                .filter_map(|result| {
-                    if let Err(error) = result {
-                        Some(error)
-                    } else {
-                        None
-                    }
-                })
-                .fold(Err(eyre!("encountered multiple errors")), |report, e| {
-                    report.error(e)
-                });
+fn write_samples<V: Default + Serialize + WideValue>(
+    mut samples: Samples<V>,
+    options: &CombineAbundances,
+    output: &Output,
+) -> Result<(), Report>
+where
+    Samples<V>: WriteAbundanceCsv + WriteWideCsv + WriteBiom,
+{
+    if options.add_missing_taxons {
+        samples.add_missing_taxons();
+    }
+
+    let mut writer = output.writer()?;
+    match options.output.format {
+        OutputAbundanceFormat::Csv if options.layout == AbundanceLayout::Wide => {
+            samples
+                .write_wide_csv(
+                    &mut writer,
+                    options.value.unwrap_or(AbundanceValue::TaxonReads),
+                    options.output.lineage,
+                )
+                .wrap_err("failed to write output to CSV")?;
+        }
+        OutputAbundanceFormat::Csv => {
+            samples
+                .write_csv(&mut writer, options.output.lineage)
+                .wrap_err("failed to write output to CSV")?;
         }
+        OutputAbundanceFormat::Biom => {
+            samples
+                .write_biom(&mut writer)
+                .wrap_err("failed to write output to BIOM")?;
+        }
+        OutputAbundanceFormat::Json => write_json(&mut writer, &samples)?,
+        OutputAbundanceFormat::Ndjson => write_ndjson_samples(&mut writer, &samples)?,
+        OutputAbundanceFormat::Mpa => {
+            MpaTable::from_samples(&samples, options.output.canonical_ranks).write(&mut writer)?
+        }
+    }
 
-        let mut samples = Samples::from_iter(ok_abundance_data.into_iter().map(Result::unwrap));
+    Ok(())
+}
 
-        if self.add_missing_taxons {
-            samples.add_missing_taxons();
+impl Runner for CombineAbundances {
+    #[instrument]
+    fn run(self) -> Result<(), Report> {
+        if self.layout == AbundanceLayout::Wide && self.output.format != OutputAbundanceFormat::Csv
+        {
+            return Err(eyre!(
+                "`--layout wide` is only available for the CSV output"
+            ))
+            .suggestion("remove `--layout wide` or use `--format csv`");
         }
-
-        let mut writer = output.writer()?;
-        match self.output.format {
-            crate::io::OutputAbundanceFormat::Csv => {
-                samples
-                    .write_csv(&mut writer)
-                    .wrap_err("failed to write output to CSV")?;
-            }
+        if self.layout == AbundanceLayout::Long && self.value.is_some() {
+            return Err(eyre!("`--value` is only used by the wide layout"))
+                .suggestion("remove `--value` or add `--layout wide`");
         }
+        check_lineage(self.output.lineage, &self.output.format)?;
 
-        Ok(())
+        let inputs = self.input.open_reports()?;
+        let taxonomy = self.input.taxonomy_for(&inputs)?;
+        let output = Output::from(self.output.file.clone());
+        output.try_writtable()?;
+
+        let bracken = inputs
+            .iter()
+            .filter(|input| input.format == InputReportFormat::Bracken)
+            .count();
+
+        if bracken == inputs.len() {
+            let samples: Samples<AbundanceValues> =
+                read_samples(inputs, |input| input.parse_bracken(taxonomy.as_ref()))?;
+
+            write_samples(samples, &self, &output)
+        } else if bracken == 0 {
+            let samples: Samples = read_samples(inputs, |input| input.parse(taxonomy.as_ref()))?;
+
+            write_samples(samples, &self, &output)
+        } else {
+            Err(eyre!(
+                "Bracken abundance tables can not be combined with other reports"
+            ))
+            .suggestion("combine the Bracken tables and the other reports separately")
+        }
     }
 }
@@ -1,61 +1,63 @@
 use color_eyre::{Help, Report};
-use libspideog::{data::tree::Tree, errors::SpideogError};
+use libspideog::data::{abundance::SampleName, tree::Tree};
 use tracing::instrument;
 
 use crate::{
     cli::subcommands::{CombineTrees, Runner},
-    io::{newick::write_newick, report::ParseKrakenReport, Output},
+    io::{
+        combine_trees, join_errors, reject_bracken, sample_name, write_tree, Output,
+        OutputPhyloFormat,
+    },
+    BinError,
 };
 
-type VecResultTrees = Vec<Result<Tree, SpideogError>>;
+type VecResultTrees = Vec<Result<(SampleName, Tree), BinError>>;
 
 impl Runner for CombineTrees {
     #[instrument]
     fn run(self) -> Result<(), Report> {
-        let readers = self.input.open_reports()?;
+        let inputs = self.input.open_reports()?;
+
+        reject_bracken(&inputs)?;
+
+        if self.output.format == OutputPhyloFormat::Krona && inputs.len() > 1 {
+            return Err(eyre!(
+                "the Krona text format holds only one sample, found {}",
+                inputs.len()
+            ))
+            .suggestion(
+                "use `--format krona-html`, or convert the reports one by one with `convert-tree`",
+            );
+        }
+
+        let taxonomy = self.input.taxonomy_for(&inputs)?;
+
         let output = Output::from(self.output.file.clone());
         output.try_writtable()?;
 
-        let (ok_trees, errors_trees): (VecResultTrees, VecResultTrees) = readers
+        let (ok_trees, errors_trees): (VecResultTrees, VecResultTrees) = inputs
             .into_iter()
-            .map(|r| -> Result<Tree, SpideogError> {
-                let mut csv_reader = csv::ReaderBuilder::new()
-                    .has_headers(self.input.headers)
-                    .delimiter(b'\t')
-                    .double_quote(false)
-                    .flexible(true)
-                    .from_reader(r);
-
-                let tree: Tree = ParseKrakenReport::parse(&mut csv_reader)?;
-
-                Ok(tree)
+            .map(|mut input| {
+                let tree = input.parse(taxonomy.as_ref())?;
+                Ok((sample_name(&input.path), tree))
             })
             .partition(Result::is_ok);
 
-        if !errors_trees.is_empty() {
-            return errors_trees
-                .into_iter()
-                .filter_map(|result| {
-                    if let Err(error) = result {
-                        Some(error)
-                    } else {
-                        None
-                    }
-                })
-                .fold(Err(eyre!("encountered multiple errors")), |report, e| {
-                    report.error(e)
-                });
-        }
-
-        let mut trees_iter = ok_trees.into_iter().map(Result::unwrap);
+        join_errors(errors_trees)?;
 
-        let combined_tree = trees_iter.try_fold(Tree::new(), Tree::try_combine_with)?;
+        let samples: Vec<(SampleName, Tree)> = ok_trees.into_iter().map(Result::unwrap).collect();
 
         let mut writer = output.writer()?;
-        match self.output.format {
-            crate::io::OutputPhyloFormat::Newick => write_newick(&mut writer, &combined_tree)?,
+
+        if self.output.format.per_sample() {
+            write_tree(&mut writer, &samples, None, &self.output)?;
+            return Ok(());
         }
 
+        let combined_tree = combine_trees(&samples, taxonomy.as_ref())?;
+
+        write_tree(&mut writer, &samples, Some(&combined_tree), &self.output)?;
+
         Ok(())
     }
 }
@@ -1,43 +1,79 @@
 use color_eyre::{Help, Report};
-use csv::ReaderBuilder;
 use eyre::Context;
-use libspideog::data::abundance::AbundanceData;
+use libspideog::{
+    bracken::BrackenData,
+    data::abundance::{AbundanceData, Samples},
+};
+use serde::Serialize;
+use std::{iter::FromIterator, path::Path};
 use tracing::instrument;
 
 use crate::{
-    cli::subcommands::{ConvertAbundance, Runner},
-    io::{abundance_csv::WriteAbundanceCsv, report::ParseKrakenReport, Output},
+    cli::{
+        args::OutputAbundance,
+        subcommands::{ConvertAbundance, Runner},
+    },
+    io::{
+        abundance_csv::{WideValue, WriteAbundanceCsv},
+        biom::WriteBiom,
+        check_lineage,
+        json::{write_json, write_ndjson_samples},
+        mpa::MpaTable,
+        sample_name, InputReportFormat, Output, OutputAbundanceFormat,
+    },
 };
 
+pub(crate) fn write_abundance<V: Serialize + WideValue>(
+    data: AbundanceData<V>,
+    input: &Path,
+    options: &OutputAbundance,
+    output: &Output,
+) -> Result<(), Report>
+where
+    AbundanceData<V>: WriteAbundanceCsv,
+    Samples<V>: WriteBiom,
+{
+    let mut writer = output.writer()?;
+    let sample = |data| Samples::from_iter(vec![(sample_name(input), data)]);
+
+    match options.format {
+        OutputAbundanceFormat::Csv => data
+            .write_csv(&mut writer, options.lineage)
+            .wrap_err("failed to write output to CSV")?,
+        OutputAbundanceFormat::Biom => sample(data)
+            .write_biom(&mut writer)
+            .wrap_err("failed to write output to BIOM")?,
+        OutputAbundanceFormat::Json => write_json(&mut writer, &sample(data))?,
+        OutputAbundanceFormat::Ndjson => write_ndjson_samples(&mut writer, &sample(data))?,
+        OutputAbundanceFormat::Mpa => {
+            MpaTable::from_samples(&sample(data), options.canonical_ranks).write(&mut writer)?
+        }
+    }
+
+    Ok(())
+}
+
 impl Runner for ConvertAbundance {
     #[instrument]
     fn run(self) -> Result<(), Report> {
-        let input = &self.input.path;
+        check_lineage(self.output.lineage, &self.output.format)?;
 
-        let reader = self.input.open_report()?;
-        let mut csv_reader = ReaderBuilder::new()
-            .has_headers(self.input.headers)
-            .delimiter(b'\t')
-            .double_quote(false)
-            .flexible(true)
-            .from_reader(reader);
+        let mut input = self.input.open_report()?;
+        let taxonomy = self.input.taxonomy_for(input.format)?;
 
-        let output = Output::from(self.output.file);
+        let output = Output::from(self.output.file.clone());
         output.try_writtable()?;
 
-        let data: AbundanceData = AbundanceData::parse(&mut csv_reader)
-            .wrap_err_with(|| format!("failed to parse file `{}`", &input.display()))
-            .suggestion("try using the `--has-headers` option if your Kraken report has headers")?;
+        if input.format == InputReportFormat::Bracken {
+            let data: BrackenData = input.parse_bracken(taxonomy.as_ref())?;
 
-        let mut writer = output.writer()?;
+            write_abundance(data, &input.path, &self.output, &output)
+        } else {
+            let data: AbundanceData = input.parse(taxonomy.as_ref()).suggestion(
+                "use the `--report-format` and `--has-headers` options if the format is not detected correctly",
+            )?;
 
-        match self.output.format {
-            crate::io::OutputAbundanceFormat::Csv => {
-                data.write_csv(&mut writer)
-                    .wrap_err("failed to write output to CSV")?;
-            }
+            write_abundance(data, &input.path, &self.output, &output)
         }
-
-        Ok(())
     }
 }
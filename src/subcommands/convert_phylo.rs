@@ -1,39 +1,36 @@
 use color_eyre::{Help, Report};
-use eyre::Context;
 use libspideog::data::tree::Tree;
 use tracing::instrument;
 
 use crate::{
     cli::subcommands::{ConvertTree, Runner},
-    io::newick::write_newick,
-    io::{report::ParseKrakenReport, Output},
+    io::{reject_bracken, sample_name, write_tree, Output},
 };
 
 impl Runner for ConvertTree {
     #[instrument]
     fn run(self) -> Result<(), Report> {
-        let input = &self.input.path;
+        let mut input = self.input.open_report()?;
 
-        let reader = self.input.open_report()?;
-        let mut csv_reader = csv::ReaderBuilder::new()
-            .has_headers(self.input.headers)
-            .delimiter(b'\t')
-            .double_quote(false)
-            .flexible(true)
-            .from_reader(reader);
+        reject_bracken(std::slice::from_ref(&input))?;
 
-        let output = Output::from(self.output.file);
+        let taxonomy = self.input.taxonomy_for(input.format)?;
+
+        let output = Output::from(self.output.file.clone());
         output.try_writtable()?;
 
-        let tree: Tree = ParseKrakenReport::parse(&mut csv_reader)
-            .wrap_err_with(|| format!("failed to parse file `{}`", &input.display()))
-            .suggestion("try using the `--has-headers` option if your Kraken report has headers")?;
+        let tree: Tree = input.parse(taxonomy.as_ref()).suggestion(
+            "use the `--report-format` and `--has-headers` options if the format is not detected correctly",
+        )?;
 
         let mut writer = output.writer()?;
 
-        match self.output.format {
-            crate::io::OutputPhyloFormat::Newick => write_newick(&mut writer, &tree)?,
-        }
+        write_tree(
+            &mut writer,
+            &[(sample_name(&input.path), tree)],
+            None,
+            &self.output,
+        )?;
 
         Ok(())
     }
@@ -0,0 +1,84 @@
+mod alpha;
+mod beta;
+mod unifrac;
+
+use color_eyre::Report;
+use libspideog::{
+    data::{
+        abundance::{AbundanceData, SampleName},
+        tree::Tree,
+    },
+    diversity::{CountedReads, RankCounts},
+    taxonomy::Rank,
+};
+use tracing::instrument;
+
+use crate::{
+    cli::{
+        args::{DiversityCounts, MultipleReports},
+        subcommands::{Diversity, DiversityCommand, Runner},
+    },
+    io::{join_errors, sample_name, InputReportFormat},
+    BinError,
+};
+
+/// Read counts of the taxa at the chosen rank of a report, with its tree when it is needed
+#[derive(Debug)]
+struct SampleCounts {
+    name: SampleName,
+    counts: RankCounts,
+    /// Not available for Bracken tables
+    tree: Option<Tree>,
+}
+
+type VecResultSampleCounts = Vec<Result<SampleCounts, BinError>>;
+
+/// Read counts of the taxa at the chosen rank of each report, Bracken tables and other reports can be
+/// mixed. The trees are read from the same reports when `with_trees` is set.
+fn read_rank_counts(
+    input: &MultipleReports,
+    counts: &DiversityCounts,
+    with_trees: bool,
+) -> Result<Vec<SampleCounts>, Report> {
+    let inputs = input.open_reports()?;
+    let taxonomy = input.taxonomy_for(&inputs)?;
+    let rank = Rank::from(counts.rank);
+    let reads = CountedReads::from(counts.reads);
+
+    let (ok_counts, errors_counts): (VecResultSampleCounts, VecResultSampleCounts) = inputs
+        .into_iter()
+        .map(|mut input| {
+            let (counts, tree) = if input.format == InputReportFormat::Bracken {
+                let data = input.parse_bracken(taxonomy.as_ref())?;
+                (RankCounts::of(&data, rank, reads), None)
+            } else if with_trees {
+                let (tree, data): (Tree, AbundanceData) = input.parse(taxonomy.as_ref())?;
+                (RankCounts::of(&data, rank, reads), Some(tree))
+            } else {
+                let data: AbundanceData = input.parse(taxonomy.as_ref())?;
+                (RankCounts::of(&data, rank, reads), None)
+            };
+
+            Ok(SampleCounts {
+                name: sample_name(&input.path),
+                counts,
+                tree,
+            })
+        })
+        .partition(Result::is_ok);
+
+    join_errors(errors_counts)?;
+
+    Ok(ok_counts.into_iter().map(Result::unwrap).collect())
+}
+
+impl Runner for Diversity {
+    #[instrument]
+    fn run(self) -> Result<(), Report> {
+        match self.command {
+            DiversityCommand::Alpha(args) => args.run(),
+            DiversityCommand::Beta(args) => args.run(),
+            DiversityCommand::Unifrac(args) => args.run(),
+        }
+    }
+}
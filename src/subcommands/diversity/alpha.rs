@@ -0,0 +1,117 @@
+use color_eyre::Report;
+use eyre::Context;
+use libspideog::diversity::{alpha::AlphaDiversity as AlphaIndices, phylogenetic::TreeDiversity};
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::instrument;
+
+use super::{read_rank_counts, SampleCounts};
+use crate::{
+    cli::subcommands::{AlphaDiversity, Runner},
+    io::{json::write_json, newick::Style, Output, OutputDiversityFormat},
+};
+
+#[derive(Debug, Serialize)]
+struct AlphaRow<'a> {
+    sample: &'a str,
+    rank: String,
+    reads: u64,
+    unclassified_reads: u64,
+    unassigned_reads: u64,
+    richness: usize,
+    shannon: Option<f64>,
+    simpson: Option<f64>,
+    inverse_simpson: Option<f64>,
+    berger_parker: Option<f64>,
+    chao1: Option<f64>,
+    fisher: Option<f64>,
+    faith_pd: Option<f64>,
+    taxonomic_diversity: Option<f64>,
+    taxonomic_distinctness: Option<f64>,
+}
+
+impl AlphaDiversity {
+    /// Faith's PD and taxonomic distinctness of the taxa at the rank, on the tree of the report
+    fn tree_diversity(&self, sample: &SampleCounts) -> Result<Option<TreeDiversity>, Report> {
+        let tree = match &sample.tree {
+            Some(tree) => tree,
+            None => return Ok(None),
+        };
+
+        let counts: HashMap<u64, u64> = sample
+            .counts
+            .counts
+            .iter()
+            .map(|(taxon, count)| (taxon.taxonomy_id, *count))
+            .collect();
+        let style = Style {
+            branch_length: self.branch_length.into(),
+            ..Style::default()
+        };
+
+        let diversity = TreeDiversity::new(tree, &counts, |node, parent| {
+            style.branch_length(node, parent)
+        })
+        .wrap_err_with(|| format!("failed to compute the tree indices of `{}`", sample.name))?;
+
+        Ok(Some(diversity))
+    }
+}
+
+impl Runner for AlphaDiversity {
+    #[instrument]
+    fn run(self) -> Result<(), Report> {
+        let output = Output::from(self.output.file.clone());
+        output.try_writtable()?;
+
+        let samples = read_rank_counts(&self.input, &self.counts, true)?;
+        let rank = self.counts.rank.to_string().to_lowercase();
+
+        let rows = samples
+            .iter()
+            .map(|sample| {
+                let counts: Vec<u64> = sample.counts.counts.values().copied().collect();
+                let indices = AlphaIndices::from_counts(&counts);
+                let tree_diversity = self.tree_diversity(sample)?;
+
+                Ok(AlphaRow {
+                    sample: &sample.name,
+                    rank: rank.clone(),
+                    reads: sample.counts.total(),
+                    unclassified_reads: sample.counts.unclassified,
+                    unassigned_reads: sample.counts.unassigned,
+                    richness: indices.richness,
+                    shannon: indices.shannon,
+                    simpson: indices.simpson,
+                    inverse_simpson: indices.inverse_simpson,
+                    berger_parker: indices.berger_parker,
+                    chao1: indices.chao1,
+                    fisher: indices.fisher,
+                    faith_pd: tree_diversity.as_ref().map(|tree| tree.faith_pd),
+                    taxonomic_diversity: tree_diversity
+                        .as_ref()
+                        .and_then(|tree| tree.taxonomic_diversity),
+                    taxonomic_distinctness: tree_diversity
+                        .as_ref()
+                        .and_then(|tree| tree.taxonomic_distinctness),
+                })
+            })
+            .collect::<Result<Vec<AlphaRow>, Report>>()?;
+
+        let mut writer = output.writer()?;
+        match self.output.format {
+            OutputDiversityFormat::Csv => {
+                let mut csv_writer = csv::Writer::from_writer(&mut writer);
+                for row in &rows {
+                    csv_writer
+                        .serialize(row)
+                        .wrap_err_with(|| format!("failed to write record for `{}`", row.sample))?;
+                }
+                csv_writer.flush()?;
+            }
+            OutputDiversityFormat::Json => write_json(&mut writer, &rows)?,
+        }
+
+        Ok(())
+    }
+}
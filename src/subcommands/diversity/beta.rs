@@ -0,0 +1,64 @@
+use color_eyre::Report;
+use eyre::Context;
+use libspideog::{
+    data::abundance::SampleName,
+    diversity::{
+        beta::{distance_matrix, Distance},
+        CountMatrix,
+    },
+};
+use tracing::instrument;
+
+use super::read_rank_counts;
+use crate::{
+    cli::subcommands::{BetaDiversity, Runner},
+    io::{
+        distance::{write_phylip, write_tsv},
+        DistanceMetric, Output, OutputDistanceFormat,
+    },
+};
+
+impl BetaDiversity {
+    fn distance(&self) -> Distance {
+        match self.metric {
+            DistanceMetric::BrayCurtis => Distance::BrayCurtis,
+            DistanceMetric::Jaccard => Distance::Jaccard,
+            DistanceMetric::Aitchison => Distance::Aitchison {
+                pseudocount: self.pseudocount,
+            },
+            DistanceMetric::Hellinger => Distance::Hellinger,
+        }
+    }
+}
+
+impl Runner for BetaDiversity {
+    #[instrument]
+    fn run(self) -> Result<(), Report> {
+        if self.metric == DistanceMetric::Aitchison
+            && (self.pseudocount.is_nan() || self.pseudocount <= 0.0)
+        {
+            return Err(eyre!(
+                "the pseudocount must be positive, got {}",
+                self.pseudocount
+            ));
+        }
+
+        let output = Output::from(self.output.file.clone());
+        output.try_writtable()?;
+
+        let samples = read_rank_counts(&self.input, &self.counts, false)?;
+        let names: Vec<SampleName> = samples.iter().map(|sample| sample.name.clone()).collect();
+        let counts = CountMatrix::from_rank_counts(samples.iter().map(|sample| &sample.counts));
+        let distance = self.distance();
+        let matrix = distance_matrix(&counts.counts, |a, b| distance.between(a, b));
+
+        let mut writer = output.writer()?;
+        match self.output.format {
+            OutputDistanceFormat::Tsv => write_tsv(&mut writer, &names, &matrix),
+            OutputDistanceFormat::Phylip => write_phylip(&mut writer, &names, &matrix),
+        }
+        .wrap_err("failed to write the distance matrix")?;
+
+        Ok(())
+    }
+}
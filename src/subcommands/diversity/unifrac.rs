@@ -0,0 +1,89 @@
+use color_eyre::Report;
+use eyre::Context;
+use libspideog::{
+    data::{abundance::SampleName, tree::Tree},
+    diversity::{
+        beta::distance_matrix,
+        unifrac::{Branches, UniFrac},
+    },
+};
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::{
+    cli::subcommands::{Runner, UnifracDistance},
+    io::{
+        combine_trees,
+        distance::{write_phylip, write_tsv},
+        join_errors,
+        newick::Style,
+        reject_bracken, sample_name, Output, OutputDistanceFormat,
+    },
+    BinError,
+};
+
+type VecResultTrees = Vec<Result<(SampleName, Tree), BinError>>;
+
+impl Runner for UnifracDistance {
+    #[instrument]
+    fn run(self) -> Result<(), Report> {
+        let inputs = self.input.open_reports()?;
+
+        reject_bracken(&inputs)?;
+
+        let taxonomy = self.input.taxonomy_for(&inputs)?;
+
+        let output = Output::from(self.output.file.clone());
+        output.try_writtable()?;
+
+        let (ok_trees, errors_trees): (VecResultTrees, VecResultTrees) = inputs
+            .into_iter()
+            .map(|mut input| {
+                let tree = input.parse(taxonomy.as_ref())?;
+                Ok((sample_name(&input.path), tree))
+            })
+            .partition(Result::is_ok);
+
+        join_errors(errors_trees)?;
+
+        let samples: Vec<(SampleName, Tree)> = ok_trees.into_iter().map(Result::unwrap).collect();
+        let combined_tree = combine_trees(&samples, taxonomy.as_ref())?;
+
+        let taxon_reads: Vec<HashMap<u64, u64>> = samples
+            .iter()
+            .map(|(_, tree)| {
+                tree.reads_by_taxonomy_id()
+                    .into_iter()
+                    .map(|(taxonomy_id, reads)| (taxonomy_id, reads.taxon))
+                    .collect()
+            })
+            .collect();
+
+        let style = Style {
+            branch_length: self.branch_length.into(),
+            ..Style::default()
+        };
+        let branches = Branches::new(&combined_tree, &taxon_reads, |node, parent| {
+            style.branch_length(node, parent)
+        })?;
+
+        let unifrac = if self.weighted {
+            UniFrac::Weighted
+        } else {
+            UniFrac::Unweighted
+        };
+        let matrix = distance_matrix(&branches.proportions, |a, b| {
+            unifrac.between(&branches.lengths, a, b)
+        });
+
+        let names: Vec<SampleName> = samples.into_iter().map(|(name, _)| name).collect();
+        let mut writer = output.writer()?;
+        match self.output.format {
+            OutputDistanceFormat::Tsv => write_tsv(&mut writer, &names, &matrix),
+            OutputDistanceFormat::Phylip => write_phylip(&mut writer, &names, &matrix),
+        }
+        .wrap_err("failed to write the distance matrix")?;
+
+        Ok(())
+    }
+}
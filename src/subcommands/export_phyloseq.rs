@@ -0,0 +1,99 @@
+use color_eyre::Report;
+use eyre::Context;
+use libspideog::data::{
+    abundance::{AbundanceData, SampleName, Samples},
+    tree::Tree,
+};
+use std::{fs::File, iter::FromIterator};
+use tracing::instrument;
+
+use crate::{
+    cli::subcommands::{ExportPhyloseq, Runner},
+    io::{
+        combine_trees, join_errors,
+        phyloseq::{otus, write_otu_table, write_otu_tree, write_sample_data, write_tax_table},
+        reject_bracken, sample_name, Output, OutputKind,
+    },
+    BinError,
+};
+
+type VecResultReports = Vec<Result<(SampleName, Tree, AbundanceData), BinError>>;
+
+impl ExportPhyloseq {
+    fn output(&self, file_name: &str) -> Output {
+        Output {
+            kind: OutputKind::File(self.directory.join(file_name)),
+            overwrite: self.overwrite,
+        }
+    }
+}
+
+impl Runner for ExportPhyloseq {
+    #[instrument]
+    fn run(self) -> Result<(), Report> {
+        let inputs = self.input.open_reports()?;
+
+        reject_bracken(&inputs)?;
+
+        let taxonomy = self.input.taxonomy_for(&inputs)?;
+
+        std::fs::create_dir_all(&self.directory).wrap_err_with(|| {
+            format!(
+                "failed to create the output directory `{}`",
+                self.directory.display()
+            )
+        })?;
+
+        let otu_table = self.output("otu_table.csv");
+        let tax_table = self.output("tax_table.csv");
+        let sample_data = self.output("sample_data.csv");
+        let tree = self.output("tree.nwk");
+        otu_table.try_writtable()?;
+        tax_table.try_writtable()?;
+        tree.try_writtable()?;
+        if self.sample_data.is_some() {
+            sample_data.try_writtable()?;
+        }
+
+        let (ok_reports, errors_reports): (VecResultReports, VecResultReports) = inputs
+            .into_iter()
+            .map(|mut input| {
+                let (tree, data): (Tree, AbundanceData) = input.parse(taxonomy.as_ref())?;
+                Ok((sample_name(&input.path), tree, data))
+            })
+            .partition(Result::is_ok);
+
+        join_errors(errors_reports)?;
+
+        let mut trees: Vec<(SampleName, Tree)> = Vec::new();
+        let mut datasets: Vec<(SampleName, AbundanceData)> = Vec::new();
+        for (name, tree, data) in ok_reports.into_iter().map(Result::unwrap) {
+            trees.push((name.clone(), tree));
+            datasets.push((name, data));
+        }
+        let samples = Samples::from_iter(datasets);
+
+        let combined_tree = combine_trees(&trees, taxonomy.as_ref())?;
+        let otus = otus(&combined_tree)?;
+
+        write_otu_table(&mut otu_table.writer()?, &otus, &samples)
+            .wrap_err("failed to write the OTU table")?;
+        write_tax_table(&mut tax_table.writer()?, &otus)
+            .wrap_err("failed to write the taxonomy table")?;
+        write_otu_tree(&mut tree.writer()?, &combined_tree).wrap_err("failed to write the tree")?;
+
+        if let Some(path) = &self.sample_data {
+            let sample_names: Vec<SampleName> = samples
+                .data
+                .iter()
+                .map(|sample| sample.name.clone())
+                .collect();
+            let reader = File::open(path)
+                .wrap_err_with(|| format!("failed to open `{}`", path.display()))?;
+            write_sample_data(reader, &mut sample_data.writer()?, &sample_names)
+                .wrap_err("failed to write the sample data")?;
+        }
+
+        Ok(())
+    }
+}
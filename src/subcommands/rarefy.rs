@@ -0,0 +1,114 @@
+use color_eyre::{Help, Report};
+use eyre::Context;
+use libspideog::{
+    data::{abundance::AbundanceData, tree::Tree},
+    diversity::rarefaction::{rarefy, report_records},
+    kraken::KrakenRecord,
+};
+use std::{collections::HashSet, path::Path};
+use tracing::instrument;
+
+use super::convert_abundance::write_abundance;
+use crate::{
+    cli::{
+        args::{OutputAbundance, OutputFile},
+        subcommands::{Rarefy, Runner},
+    },
+    io::{
+        check_lineage, reject_bracken,
+        report::{write_report, FromKrakenRecords},
+        sample_name, Output, OutputKind,
+    },
+};
+
+impl Rarefy {
+    fn output(&self, path: &Path) -> Output {
+        let file_name = format!("{}.{}", sample_name(path), self.output.format.extension());
+
+        Output {
+            kind: OutputKind::File(self.output.directory.join(file_name)),
+            overwrite: self.output.overwrite,
+        }
+    }
+
+    /// Subsample a tree and write it to its own file in the output directory
+    fn rarefy_report(&self, path: &Path, tree: &Tree) -> Result<(), Report> {
+        let output = self.output(path);
+
+        let rarefied = rarefy(tree, self.depth, self.seed)
+            .wrap_err_with(|| format!("failed to rarefy `{}`", path.display()))
+            .suggestion("use a lower `--depth`")?;
+        let records = report_records(&rarefied)?;
+
+        match self.output.format.abundance_format() {
+            None => write_report(&mut output.writer()?, &records)
+                .wrap_err("failed to write the Kraken report")?,
+            Some(format) => {
+                let data = AbundanceData::from_records(
+                    records
+                        .into_iter()
+                        .map(|record| Ok(KrakenRecord::Standard(record))),
+                )?;
+                let options = OutputAbundance {
+                    file: OutputFile {
+                        path: None,
+                        overwrite: self.output.overwrite,
+                    },
+                    format,
+                    canonical_ranks: self.output.canonical_ranks,
+                    lineage: self.output.lineage,
+                };
+
+                write_abundance(data, path, &options, &output)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Runner for Rarefy {
+    #[instrument]
+    fn run(self) -> Result<(), Report> {
+        if let Some(format) = self.output.format.abundance_format() {
+            check_lineage(self.output.lineage, &format)?;
+        }
+
+        let inputs = self.input.open_reports()?;
+        reject_bracken(&inputs)?;
+
+        let mut names = HashSet::new();
+        if let Some(input) = inputs
+            .iter()
+            .find(|input| !names.insert(sample_name(&input.path)))
+        {
+            return Err(eyre!(
+                "several reports are named `{}`",
+                sample_name(&input.path)
+            ))
+            .suggestion("the output files are named after the reports, rename the reports with the same name");
+        }
+
+        let taxonomy = self.input.taxonomy_for(&inputs)?;
+
+        std::fs::create_dir_all(&self.output.directory).wrap_err_with(|| {
+            format!(
+                "failed to create the output directory `{}`",
+                self.output.directory.display()
+            )
+        })?;
+        for input in &inputs {
+            self.output(&input.path).try_writtable()?;
+        }
+
+        for mut input in inputs {
+            let tree: Tree = input.parse(taxonomy.as_ref()).suggestion(
+                "use the `--report-format` and `--has-headers` options if the format is not detected correctly",
+            )?;
+
+            self.rarefy_report(&input.path, &tree)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,3 +1,7 @@
+pub mod index;
+pub mod ktaxonomy;
+pub mod taxdump;
+
 use std::fmt::Display;
 
 use once_cell::sync::Lazy;
@@ -45,6 +49,67 @@ impl Display for Rank {
 }
 
 impl Rank {
+    /// Letter of the rank code in Kraken reports, e.g. `G` for `G1`
+    #[must_use]
+    pub fn letter(&self) -> char {
+        match self {
+            Self::Unclassified(_) => 'U',
+            Self::Root(_) => 'R',
+            Self::Domain(_) => 'D',
+            Self::Kingdom(_) => 'K',
+            Self::Phylum(_) => 'P',
+            Self::Class(_) => 'C',
+            Self::Order(_) => 'O',
+            Self::Family(_) => 'F',
+            Self::Genus(_) => 'G',
+            Self::Species(_) => 'S',
+        }
+    }
+
+    /// Offset of the rank code in Kraken reports, e.g. `1` for `G1`
+    #[must_use]
+    pub fn offset(&self) -> u32 {
+        match self {
+            Self::Unclassified(i)
+            | Self::Root(i)
+            | Self::Domain(i)
+            | Self::Kingdom(i)
+            | Self::Phylum(i)
+            | Self::Class(i)
+            | Self::Order(i)
+            | Self::Family(i)
+            | Self::Genus(i)
+            | Self::Species(i) => *i,
+        }
+    }
+
+    /// Depth of the canonical rank below the root, e.g. `1` for a domain and `8` for `S` or `S1`
+    #[must_use]
+    pub fn depth(&self) -> u32 {
+        match self {
+            Self::Unclassified(_) | Self::Root(_) => 0,
+            Self::Domain(_) => 1,
+            Self::Kingdom(_) => 2,
+            Self::Phylum(_) => 3,
+            Self::Class(_) => 4,
+            Self::Order(_) => 5,
+            Self::Family(_) => 6,
+            Self::Genus(_) => 7,
+            Self::Species(_) => 8,
+        }
+    }
+
+    /// Number of rank steps from an ancestor rank: one per canonical rank and one per sub-rank
+    /// offset, e.g. `1` from `S` to `S1` and `2` from `G` to `S1`
+    #[must_use]
+    pub fn steps_from(&self, ancestor: &Self) -> u32 {
+        if self.depth() == ancestor.depth() {
+            self.offset().saturating_sub(ancestor.offset())
+        } else {
+            self.depth().saturating_sub(ancestor.depth()) + self.offset()
+        }
+    }
+
     #[must_use]
     pub fn plus_one(self) -> Self {
         match self {
@@ -124,6 +189,22 @@ pub fn parse_taxonomy_level(string: &str) -> Result<Rank, TaxRankParsingError> {
     Ok(tax_rank)
 }
 
+/// Kraken rank code of the NCBI ranks printed in Kraken reports
+#[must_use]
+pub fn canonical_rank(ncbi_rank: &str) -> Option<Rank> {
+    match ncbi_rank {
+        "superkingdom" | "domain" => Some(Rank::Domain(0)),
+        "kingdom" => Some(Rank::Kingdom(0)),
+        "phylum" => Some(Rank::Phylum(0)),
+        "class" => Some(Rank::Class(0)),
+        "order" => Some(Rank::Order(0)),
+        "family" => Some(Rank::Family(0)),
+        "genus" => Some(Rank::Genus(0)),
+        "species" => Some(Rank::Species(0)),
+        _ => None,
+    }
+}
+
 impl<'de> Deserialize<'de> for Rank {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -177,6 +258,18 @@ mod tests {
         pretty_assertions::assert_eq!(Rank::Kingdom(2).plus_one(), Rank::Kingdom(3))
     }
 
+    #[test_case(Rank::Species(1), Rank::Species(0) => 1 ; "sub-species")]
+    #[test_case(Rank::Genus(1), Rank::Genus(0) => 1 ; "sub-genus")]
+    #[test_case(Rank::Root(1), Rank::Root(0) => 1 ; "sub-root")]
+    #[test_case(Rank::Species(2), Rank::Species(1) => 1 ; "sub-sub-species")]
+    #[test_case(Rank::Species(0), Rank::Genus(0) => 1 ; "canonical")]
+    #[test_case(Rank::Species(0), Rank::Genus(1) => 1 ; "from a sub-rank")]
+    #[test_case(Rank::Species(1), Rank::Genus(0) => 2 ; "to a sub-rank")]
+    #[test_case(Rank::Phylum(0), Rank::Domain(0) => 2 ; "missing kingdom")]
+    fn test_steps_from(rank: Rank, ancestor: Rank) -> u32 {
+        rank.steps_from(&ancestor)
+    }
+
     #[test]
     #[should_panic]
     fn test_parse_tax_level_error_too_long() {
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use crate::{errors::SpideogError, kraken::Taxon, taxonomy::Rank};
+
+pub const ROOT_TAXONOMY_ID: u64 = 1;
+
+/// Node of a reference taxonomy
+#[derive(Clone, PartialEq, Debug)]
+pub struct TaxonomyNode {
+    pub taxonomy_id: u64,
+    pub parent_id: u64,
+    pub name: String,
+    pub taxonomy_level: Rank,
+}
+
+impl From<&TaxonomyNode> for Taxon {
+    fn from(node: &TaxonomyNode) -> Self {
+        Self {
+            taxonomy_level: node.taxonomy_level,
+            name: node.name.clone(),
+            taxonomy_id: node.taxonomy_id,
+        }
+    }
+}
+
+/// Reference taxonomy indexed by taxonomy id, loaded from a NCBI taxdump or from a Kraken2 `ktaxonomy.tsv`
+#[derive(Debug, Default, Clone)]
+pub struct TaxonomyIndex {
+    nodes: HashMap<u64, TaxonomyNode>,
+    merged: HashMap<u64, u64>,
+}
+
+impl TaxonomyIndex {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            nodes: HashMap::new(),
+            merged: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, node: TaxonomyNode) -> &mut Self {
+        self.nodes.insert(node.taxonomy_id, node);
+        self
+    }
+
+    /// Record a taxonomy id merged into another one (NCBI `merged.dmp`)
+    pub fn insert_merged(&mut self, old_taxonomy_id: u64, new_taxonomy_id: u64) -> &mut Self {
+        self.merged.insert(old_taxonomy_id, new_taxonomy_id);
+        self
+    }
+
+    /// Current taxonomy id of `taxonomy_id`, following merged ids
+    #[must_use]
+    pub fn resolve_id(&self, taxonomy_id: u64) -> Option<u64> {
+        let mut current = taxonomy_id;
+
+        for _ in 0..=self.merged.len() {
+            if self.contains(current) {
+                return Some(current);
+            }
+            current = *self.merged.get(&current)?;
+        }
+
+        None
+    }
+
+    /// Check a taxon against the taxonomy: merged ids are replaced by the current ones and the rank
+    /// is taken from the taxonomy. Unclassified (taxonomy id 0) is kept as is, `None` when the
+    /// taxonomy id is unknown.
+    #[must_use]
+    pub fn resolve_taxon(&self, taxon: &Taxon) -> Option<Taxon> {
+        if taxon.taxonomy_id == 0 {
+            return Some(taxon.clone());
+        }
+
+        let node = self
+            .resolve_id(taxon.taxonomy_id)
+            .and_then(|taxonomy_id| self.get(taxonomy_id))?;
+
+        Some(Taxon {
+            taxonomy_level: node.taxonomy_level,
+            name: taxon.name.clone(),
+            taxonomy_id: node.taxonomy_id,
+        })
+    }
+
+    #[must_use]
+    pub fn get(&self, taxonomy_id: u64) -> Option<&TaxonomyNode> {
+        self.nodes.get(&taxonomy_id)
+    }
+
+    #[must_use]
+    pub fn contains(&self, taxonomy_id: u64) -> bool {
+        self.nodes.contains_key(&taxonomy_id)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Parent of a node, `None` for the root (its own parent in NCBI and Kraken taxonomies)
+    #[must_use]
+    pub fn parent(&self, taxonomy_id: u64) -> Option<u64> {
+        self.get(taxonomy_id).and_then(|node| {
+            if node.parent_id == node.taxonomy_id
+                || node.parent_id == 0
+                || !self.contains(node.parent_id)
+            {
+                None
+            } else {
+                Some(node.parent_id)
+            }
+        })
+    }
+
+    /// Taxonomy ids from the root to `taxonomy_id` (included)
+    pub fn lineage(&self, taxonomy_id: u64) -> Result<Vec<u64>, SpideogError> {
+        if !self.contains(taxonomy_id) {
+            return Err(SpideogError::UnknownTaxonomyId(taxonomy_id));
+        }
+
+        let mut lineage = vec![taxonomy_id];
+        let mut current = taxonomy_id;
+
+        while let Some(parent) = self.parent(current) {
+            // guard against cycles in malformed taxonomies
+            if lineage.len() > self.nodes.len() {
+                return Err(SpideogError::UnknownTaxonomyId(taxonomy_id));
+            }
+            lineage.push(parent);
+            current = parent;
+        }
+
+        lineage.reverse();
+        Ok(lineage)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn small_taxonomy() -> TaxonomyIndex {
+        let mut taxonomy = TaxonomyIndex::new();
+        for (taxonomy_id, parent_id, name, taxonomy_level) in [
+            (1, 1, "root", Rank::Root(0)),
+            (131_567, 1, "cellular organisms", Rank::Root(1)),
+            (2, 131_567, "Bacteria", Rank::Domain(0)),
+            (1224, 2, "Proteobacteria", Rank::Phylum(0)),
+            (562, 1224, "Escherichia coli", Rank::Species(0)),
+            (1239, 2, "Firmicutes", Rank::Phylum(0)),
+        ]
+        .iter()
+        .cloned()
+        {
+            taxonomy.insert(TaxonomyNode {
+                taxonomy_id,
+                parent_id,
+                name: name.to_string(),
+                taxonomy_level,
+            });
+        }
+        taxonomy
+    }
+
+    #[test]
+    fn test_lineage() {
+        let taxonomy = small_taxonomy();
+
+        pretty_assertions::assert_eq!(
+            taxonomy.lineage(562).unwrap(),
+            vec![1, 131_567, 2, 1224, 562]
+        );
+        pretty_assertions::assert_eq!(taxonomy.lineage(1).unwrap(), vec![1]);
+        assert!(taxonomy.lineage(9606).is_err());
+    }
+
+    #[test]
+    fn test_resolve_taxon() {
+        let mut taxonomy = small_taxonomy();
+        taxonomy
+            .insert_merged(1_000, 1_001)
+            .insert_merged(1_001, 562);
+
+        let taxon = Taxon {
+            taxonomy_level: Rank::Unclassified(0),
+            name: "Escherichia coli".to_string(),
+            taxonomy_id: 1_000,
+        };
+
+        pretty_assertions::assert_eq!(taxonomy.resolve_id(1_000), Some(562));
+        pretty_assertions::assert_eq!(
+            taxonomy.resolve_taxon(&taxon).unwrap(),
+            Taxon {
+                taxonomy_level: Rank::Species(0),
+                name: "Escherichia coli".to_string(),
+                taxonomy_id: 562,
+            }
+        );
+        assert!(taxonomy
+            .resolve_taxon(&Taxon {
+                taxonomy_id: 9606,
+                ..taxon
+            })
+            .is_none());
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::{BufRead, BufReader, Read};
+
+use tracing::instrument;
+
+use crate::{
+    errors::SpideogError,
+    taxonomy::{
+        index::{TaxonomyIndex, TaxonomyNode},
+        parse_taxonomy_level,
+        taxdump::{dmp_fields, parse_taxonomy_id},
+    },
+};
+
+impl TaxonomyIndex {
+    /// Load a taxonomy from the `ktaxonomy.tsv` file of a Kraken2 database
+    ///
+    /// columns: taxid, parent taxid, Kraken rank code, depth, name
+    #[instrument(skip(reader))]
+    pub fn from_ktaxonomy<R: Read>(reader: R) -> Result<Self, SpideogError> {
+        let mut taxonomy = Self::new();
+
+        for (line_number, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line.map_err(SpideogError::TaxonomyIo)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let fields = dmp_fields(&line);
+            let taxonomy_id = parse_taxonomy_id(fields.first(), line_number + 1)?;
+            let parent_id = parse_taxonomy_id(fields.get(1), line_number + 1)?;
+            let taxonomy_level = fields
+                .get(2)
+                .and_then(|code| parse_taxonomy_level(code.trim()).ok())
+                .ok_or(SpideogError::TaxonomyLineParsing(line_number + 1))?;
+            let name = fields
+                .get(4)
+                .ok_or(SpideogError::TaxonomyLineParsing(line_number + 1))?;
+
+            taxonomy.insert(TaxonomyNode {
+                taxonomy_id,
+                parent_id,
+                name: name.trim().to_string(),
+                taxonomy_level,
+            });
+        }
+
+        Ok(taxonomy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::taxonomy::Rank;
+
+    static KTAXONOMY: &str = "1\t|\t1\t|\tR\t|\t0\t|\troot
+131567\t|\t1\t|\tR1\t|\t1\t|\tcellular organisms
+2\t|\t131567\t|\tD\t|\t2\t|\tBacteria
+1224\t|\t2\t|\tP\t|\t3\t|\tProteobacteria
+";
+
+    #[test]
+    fn test_from_ktaxonomy() {
+        let taxonomy = TaxonomyIndex::from_ktaxonomy(KTAXONOMY.as_bytes()).unwrap();
+
+        pretty_assertions::assert_eq!(taxonomy.len(), 4);
+        pretty_assertions::assert_eq!(taxonomy.get(131_567).unwrap().taxonomy_level, Rank::Root(1));
+        pretty_assertions::assert_eq!(taxonomy.get(1224).unwrap().name, "Proteobacteria");
+        pretty_assertions::assert_eq!(taxonomy.lineage(1224).unwrap(), vec![1, 131_567, 2, 1224]);
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read},
+};
+
+use tracing::instrument;
+
+use crate::{
+    errors::SpideogError,
+    taxonomy::{
+        canonical_rank,
+        index::{TaxonomyIndex, TaxonomyNode, ROOT_TAXONOMY_ID},
+        Rank,
+    },
+};
+
+/// Split a line of a NCBI `.dmp` file, fields are delimited by `\t|\t` and lines end with `\t|`
+pub(crate) fn dmp_fields(line: &str) -> Vec<&str> {
+    line.trim_end_matches(['\n', '\r'])
+        .trim_end_matches("\t|")
+        .split("\t|\t")
+        .collect()
+}
+
+pub(crate) fn parse_taxonomy_id(
+    field: Option<&&str>,
+    line_number: usize,
+) -> Result<u64, SpideogError> {
+    field
+        .and_then(|f| f.trim().parse::<u64>().ok())
+        .ok_or(SpideogError::TaxonomyLineParsing(line_number))
+}
+
+/// Kraken rank code of a node: canonical ranks as is, other ranks are offsets of the closest canonical ancestor (e.g. `G1`)
+fn resolve_rank(
+    taxonomy_id: u64,
+    raw_nodes: &HashMap<u64, (u64, String)>,
+    resolved: &mut HashMap<u64, Rank>,
+) -> Rank {
+    let mut path = Vec::new();
+    let mut current = taxonomy_id;
+
+    let mut rank = loop {
+        if let Some(rank) = resolved.get(&current) {
+            break *rank;
+        }
+
+        let (parent_id, ncbi_rank) = match raw_nodes.get(&current) {
+            Some(raw_node) => raw_node,
+            None => break Rank::Root(0),
+        };
+
+        if current == ROOT_TAXONOMY_ID || *parent_id == current || path.len() > raw_nodes.len() {
+            resolved.insert(current, Rank::Root(0));
+            break Rank::Root(0);
+        }
+
+        if let Some(rank) = canonical_rank(ncbi_rank) {
+            resolved.insert(current, rank);
+            break rank;
+        }
+
+        path.push(current);
+        current = *parent_id;
+    };
+
+    for id in path.into_iter().rev() {
+        rank = rank.plus_one();
+        resolved.insert(id, rank);
+    }
+
+    rank
+}
+
+impl TaxonomyIndex {
+    /// Load a taxonomy from the `nodes.dmp` and `names.dmp` files of a NCBI taxdump
+    #[instrument(skip(nodes, names))]
+    pub fn from_taxdump<N: Read, M: Read>(nodes: N, names: M) -> Result<Self, SpideogError> {
+        let mut raw_nodes: HashMap<u64, (u64, String)> = HashMap::new();
+
+        for (line_number, line) in BufReader::new(nodes).lines().enumerate() {
+            let line = line.map_err(SpideogError::TaxonomyIo)?;
+            let fields = dmp_fields(&line);
+            let taxonomy_id = parse_taxonomy_id(fields.first(), line_number + 1)?;
+            let parent_id = parse_taxonomy_id(fields.get(1), line_number + 1)?;
+            let ncbi_rank = fields
+                .get(2)
+                .ok_or(SpideogError::TaxonomyLineParsing(line_number + 1))?;
+
+            raw_nodes.insert(taxonomy_id, (parent_id, ncbi_rank.trim().to_string()));
+        }
+
+        let mut scientific_names: HashMap<u64, String> = HashMap::new();
+
+        for (line_number, line) in BufReader::new(names).lines().enumerate() {
+            let line = line.map_err(SpideogError::TaxonomyIo)?;
+            let fields = dmp_fields(&line);
+
+            if fields.get(3).map(|f| f.trim()) != Some("scientific name") {
+                continue;
+            }
+
+            let taxonomy_id = parse_taxonomy_id(fields.first(), line_number + 1)?;
+            let name = fields
+                .get(1)
+                .ok_or(SpideogError::TaxonomyLineParsing(line_number + 1))?;
+
+            scientific_names.insert(taxonomy_id, name.trim().to_string());
+        }
+
+        let mut ranks: HashMap<u64, Rank> = HashMap::new();
+        let mut taxonomy = Self::new();
+
+        for (taxonomy_id, (parent_id, _)) in &raw_nodes {
+            let taxonomy_level = resolve_rank(*taxonomy_id, &raw_nodes, &mut ranks);
+            let name = scientific_names
+                .remove(taxonomy_id)
+                .unwrap_or_else(|| taxonomy_id.to_string());
+
+            taxonomy.insert(TaxonomyNode {
+                taxonomy_id: *taxonomy_id,
+                parent_id: *parent_id,
+                name,
+                taxonomy_level,
+            });
+        }
+
+        Ok(taxonomy)
+    }
+
+    /// Load the `merged.dmp` file of a NCBI taxdump, old taxonomy ids are then resolved to the new ones
+    #[instrument(skip(self, merged))]
+    pub fn read_merged<R: Read>(&mut self, merged: R) -> Result<&mut Self, SpideogError> {
+        for (line_number, line) in BufReader::new(merged).lines().enumerate() {
+            let line = line.map_err(SpideogError::TaxonomyIo)?;
+            let fields = dmp_fields(&line);
+            let old_taxonomy_id = parse_taxonomy_id(fields.first(), line_number + 1)?;
+            let new_taxonomy_id = parse_taxonomy_id(fields.get(1), line_number + 1)?;
+
+            self.insert_merged(old_taxonomy_id, new_taxonomy_id);
+        }
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static NODES: &str = "1\t|\t1\t|\tno rank\t|\t\t|\t8\t|
+131567\t|\t1\t|\tno rank\t|\t\t|\t8\t|
+2\t|\t131567\t|\tsuperkingdom\t|\t\t|\t0\t|
+1224\t|\t2\t|\tphylum\t|\t\t|\t0\t|
+1236\t|\t1224\t|\tclass\t|\t\t|\t0\t|
+91347\t|\t1236\t|\torder\t|\t\t|\t0\t|
+543\t|\t91347\t|\tfamily\t|\t\t|\t0\t|
+561\t|\t543\t|\tgenus\t|\t\t|\t0\t|
+562\t|\t561\t|\tspecies\t|\tEC\t|\t0\t|
+83333\t|\t562\t|\tstrain\t|\t\t|\t0\t|
+";
+
+    static NAMES: &str = "1\t|\tall\t|\t\t|\tsynonym\t|
+1\t|\troot\t|\t\t|\tscientific name\t|
+131567\t|\tcellular organisms\t|\t\t|\tscientific name\t|
+2\t|\tBacteria\t|\tBacteria <bacteria>\t|\tscientific name\t|
+1224\t|\tProteobacteria\t|\t\t|\tscientific name\t|
+1236\t|\tGammaproteobacteria\t|\t\t|\tscientific name\t|
+91347\t|\tEnterobacterales\t|\t\t|\tscientific name\t|
+543\t|\tEnterobacteriaceae\t|\t\t|\tscientific name\t|
+561\t|\tEscherichia\t|\t\t|\tscientific name\t|
+562\t|\tEscherichia coli\t|\t\t|\tscientific name\t|
+83333\t|\tEscherichia coli K-12\t|\t\t|\tscientific name\t|
+";
+
+    #[test]
+    fn test_from_taxdump() {
+        let taxonomy = TaxonomyIndex::from_taxdump(NODES.as_bytes(), NAMES.as_bytes()).unwrap();
+
+        pretty_assertions::assert_eq!(taxonomy.len(), 10);
+        pretty_assertions::assert_eq!(taxonomy.get(1).unwrap().name, "root");
+        pretty_assertions::assert_eq!(taxonomy.get(1).unwrap().taxonomy_level, Rank::Root(0));
+        pretty_assertions::assert_eq!(taxonomy.get(131_567).unwrap().taxonomy_level, Rank::Root(1));
+        pretty_assertions::assert_eq!(taxonomy.get(2).unwrap().taxonomy_level, Rank::Domain(0));
+        pretty_assertions::assert_eq!(
+            taxonomy.get(83333).unwrap().taxonomy_level,
+            Rank::Species(1)
+        );
+        pretty_assertions::assert_eq!(taxonomy.parent(562), Some(561));
+        pretty_assertions::assert_eq!(taxonomy.parent(1), None);
+    }
+
+    #[test]
+    fn test_read_merged() {
+        let mut taxonomy = TaxonomyIndex::from_taxdump(NODES.as_bytes(), NAMES.as_bytes()).unwrap();
+        taxonomy.read_merged("12\t|\t562\t|\n".as_bytes()).unwrap();
+
+        pretty_assertions::assert_eq!(taxonomy.resolve_id(12), Some(562));
+        pretty_assertions::assert_eq!(taxonomy.resolve_id(13), None);
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+/// Run spideog from the root of the crate, with the default log filter
+fn spideog(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_spideog"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap()
+}
+
+/// Path of a file in the temporary directory, unique to this test run
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("spideog-{}-{}", std::process::id(), name))
+}
+
+/// Compare the stdout of a successful run with an expected file from the sample data, the rows of
+/// the abundance tables are sorted as their order is not fixed
+fn assert_output(args: &[&str], expected: &str) {
+    let output = spideog(args);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let expected = fs::read_to_string(format!("tests/sample_data/{}", expected)).unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    if args[0] == "convert-abundance" {
+        let mut expected: Vec<&str> = expected.lines().collect();
+        let mut stdout: Vec<&str> = stdout.lines().collect();
+        expected[1..].sort_unstable();
+        stdout[1..].sort_unstable();
+        pretty_assertions::assert_eq!(stdout, expected);
+    } else {
+        pretty_assertions::assert_eq!(stdout, expected);
+    }
+}
+
+#[test]
+fn test_logs_on_stderr() {
+    let output = spideog(&["convert-abundance", "tests/sample_data/sample.kreport"]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with(
+        "\"taxon\",\"taxid\",\"rank\",\"clade_percentage\",\"clade_count_reads\",\"taxon_count_reads\"\n"
+    ));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("detected format `Kraken`"));
+}
+
+#[test]
+fn test_taxonomy_warnings_on_stderr() {
+    let report = temp_path("unknown_taxid.kreport");
+    fs::write(
+        &report,
+        "100.00\t10\t0\tR\t1\troot\n100.00\t10\t10\tS\t999999999\t  Unknown species\n",
+    )
+    .unwrap();
+
+    let tree = spideog(&[
+        "convert-tree",
+        report.to_str().unwrap(),
+        "--taxonomy",
+        "tests/sample_data/ktaxonomy.tsv",
+    ]);
+    let abundance = spideog(&[
+        "convert-abundance",
+        report.to_str().unwrap(),
+        "--taxonomy",
+        "tests/sample_data/ktaxonomy.tsv",
+    ]);
+    fs::remove_file(&report).unwrap();
+
+    assert!(tree.status.success());
+    pretty_assertions::assert_eq!(
+        String::from_utf8(tree.stdout).unwrap(),
+        "(Unknown_species:1)root:0;\n"
+    );
+    assert!(String::from_utf8_lossy(&tree.stderr).contains("is not in the taxonomy"));
+
+    assert!(abundance.status.success());
+    assert!(String::from_utf8(abundance.stdout)
+        .unwrap()
+        .starts_with("\"taxon\",\"taxid\""));
+    assert!(String::from_utf8_lossy(&abundance.stderr).contains("is not in the taxonomy"));
+}
+
+#[test]
+fn test_metaphlan_without_reads() {
+    let output = spideog(&["convert-abundance", "tests/sample_data/sample.metaphlan"]);
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("-t rel_ab_w_read_stats"));
+}
+
+#[test]
+fn test_convert_bracken() {
+    assert_output(
+        &["convert-abundance", "tests/sample_data/sample.bracken"],
+        "converted_bracken.csv",
+    );
+
+    let tree = spideog(&["convert-tree", "tests/sample_data/sample.bracken"]);
+    assert!(tree.stdout.is_empty());
+    assert!(
+        String::from_utf8_lossy(&tree.stderr).contains("use the Kraken report given to Bracken")
+    );
+}
+
+#[test]
+fn test_convert_kraken_minimizers() {
+    assert_output(
+        &[
+            "convert-abundance",
+            "tests/sample_data/sample_minimizers.kreport",
+        ],
+        "converted_minimizers.csv",
+    );
+    assert_output(
+        &[
+            "convert-tree",
+            "tests/sample_data/sample_minimizers.kreport",
+        ],
+        "converted_minimizers.tree",
+    );
+}
+
+#[test]
+fn test_convert_kraken_reads() {
+    assert_output(
+        &[
+            "convert-abundance",
+            "tests/sample_data/sample.kraken",
+            "--taxonomy",
+            "tests/sample_data/ktaxonomy.tsv",
+        ],
+        "converted_reads.csv",
+    );
+    assert_output(
+        &[
+            "convert-tree",
+            "tests/sample_data/sample.kraken",
+            "--taxonomy",
+            "tests/sample_data/ktaxonomy.tsv",
+        ],
+        "converted_reads.tree",
+    );
+}
+
+#[test]
+fn test_convert_krakenuniq() {
+    assert_output(
+        &["convert-abundance", "tests/sample_data/sample.krakenuniq"],
+        "converted_krakenuniq.csv",
+    );
+    assert_output(
+        &["convert-tree", "tests/sample_data/sample.krakenuniq"],
+        "converted_krakenuniq.tree",
+    );
+}
+
+#[test]
+fn test_value_without_wide_layout() {
+    let output = spideog(&[
+        "combine-abundances",
+        "tests/sample_data/sample.kreport",
+        "tests/sample_data/sample_2.kreport",
+        "--value",
+        "percentage",
+    ]);
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("`--value` is only used by the wide layout"));
+}
+
+#[test]
+fn test_diversity_branch_length_none() {
+    for subcommand in &["alpha", "unifrac"] {
+        let output = spideog(&[
+            "diversity",
+            subcommand,
+            "tests/sample_data/sample.kreport",
+            "--branch-length",
+            "none",
+        ]);
+
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("'none' isn't a valid value"));
+    }
+}